@@ -2321,8 +2321,8 @@ function updateUdpStatsUI(stats) {
   }
   
   const lossRate = (stats.loss_rate || 0).toFixed(1);
-  const bufferMs = (stats.jitter_buffer_size || 0) * 10; // 10ms per frame
-  const targetMs = ((stats.jitter_buffer_target || stats.jitter_buffer_size) || 0) * 10;
+  const bufferMs = Math.round(stats.jitter_buffer_ms || 0);
+  const targetMs = Math.round(stats.jitter_buffer_target_ms || 0);
   let quality = 'good';
   if (stats.loss_rate > 5) quality = 'bad';
   else if (stats.loss_rate > 1) quality = 'warning';
@@ -4074,7 +4074,7 @@ function updateJitterBuffer(value) {
   
   // Tauri UDP 지터 버퍼도 설정
   if (actuallyTauri) {
-    tauriInvoke('set_jitter_buffer', { size: Math.round(jitterBuffer / 10) }).catch(e => { if (DEBUG) console.debug('Silent error:', e); });
+    tauriInvoke('set_jitter_buffer', { delayMs: jitterBuffer }).catch(e => { if (DEBUG) console.debug('Silent error:', e); });
   }
 }
 
@@ -5187,7 +5187,7 @@ function applyLowLatencyMode() {
   
   // Apply to Tauri UDP if available
   if (actuallyTauri) {
-    tauriInvoke('set_jitter_buffer', { size: lowLatencyMode ? 1 : Math.round(jitterBuffer / 10) }).catch(e => { if (DEBUG) console.debug('Silent error:', e); });
+    tauriInvoke('set_jitter_buffer', { delayMs: lowLatencyMode ? 10 : jitterBuffer }).catch(e => { if (DEBUG) console.debug('Silent error:', e); });
   }
 }
 
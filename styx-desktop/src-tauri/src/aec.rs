@@ -0,0 +1,621 @@
+// 에코 제거: 재생 믹스를 기준 신호로 쓰는 주파수 영역 적응 필터 (분할 블록 NLMS) + 지연 추정
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::denoise::LoadMeter;
+use crate::fft::Fft;
+use crate::peer::{AUDIO_RING_SAMPLES, CHANNELS, SAMPLE_RATE};
+
+const BLOCK: usize = 256;            // 5.3ms, which is also the delay the canceller adds
+const FFT_SIZE: usize = 2 * BLOCK;   // Overlap-save
+const BINS: usize = BLOCK + 1;
+const PARTITIONS: usize = 12;        // 64ms of echo tail after the estimated delay
+const MAX_DELAY_BLOCKS: usize = 94;  // ~500ms from the render tap to the microphone
+const HISTORY_BLOCKS: usize = MAX_DELAY_BLOCKS + 2;
+const DELAY_MARGIN_BLOCKS: usize = 1; // The filter starts this far before the estimate
+const QUEUE_WINDOW_BLOCKS: usize = 188; // ~1s; standing render backlog is dropped once per window
+
+const STEP: f32 = 0.5;
+const MIN_STEP_RATIO: f32 = 0.1; // Lowest step until the filter first converges
+const CONVERGED_ERLE: f32 = 4.0; // 6dB: from here on each bin's step follows its echo-to-output ratio alone
+const REGULARIZATION: f32 = FFT_SIZE as f32 * PARTITIONS as f32 * 1e-6; // About -60dBFS of render power
+const RENDER_ACTIVE_POWER: f32 = 1e-6; // Mean square; the filter only adapts while the far end plays
+const POWER_SMOOTHING: f32 = 0.9;
+const SPECTRUM_SMOOTHING: f32 = 0.6; // Per-bin step control; fast, so double talk is caught at its onset
+
+// Delay estimation on binary spectra: one bit per band, set when the band is above its
+// running mean. The render history lag whose bits best match the capture is the delay.
+const BANDS: usize = 32;
+const FIRST_BAND_BIN: usize = 4; // 375Hz; bands are two bins wide, up to 6.4kHz
+const BAND_MEAN_RATE: f32 = 0.05;
+const COST_RATE: f32 = 0.02;
+const DELAY_CONFIDENCE: f32 = 0.7;   // Best lag's mismatch against the average over all lags
+const DELAY_SWITCH_RATIO: f32 = 0.9; // A new lag must beat the current one by this much
+
+/// Delay the canceller adds while enabled
+pub const LATENCY_MS: f32 = BLOCK as f32 * 1000.0 / SAMPLE_RATE as f32;
+
+const NO_DELAY: i32 = -1;
+
+/// Shared by the output callback (render reference), every send track's canceller and the
+/// commands. Render audio reaches each canceller through its own SPSC ring.
+pub struct EchoControl {
+    enabled: AtomicBool,
+    delay_blocks: AtomicI32, // Main track's estimate, NO_DELAY until found
+    erle_db: AtomicU32,      // f32 bits
+    load: LoadMeter,
+    pending: Mutex<Vec<(u8, HeapProd<f32>)>>, // Rings created before the output stream starts
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EchoCancellerStats {
+    pub enabled: bool,
+    pub delay_ms: Option<f32>, // Render to capture, None until far-end audio has been matched
+    pub erle_db: f32,          // Echo return loss enhancement on the main track
+    pub cpu_percent: f32,      // Share of one core since the previous read
+    pub latency_ms: f32,
+}
+
+impl EchoControl {
+    pub fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            delay_blocks: AtomicI32::new(NO_DELAY),
+            erle_db: AtomicU32::new(0),
+            load: LoadMeter::new(),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> EchoCancellerStats {
+        let enabled = self.enabled.load(Ordering::Relaxed);
+        let delay_blocks = self.delay_blocks.load(Ordering::Relaxed);
+        EchoCancellerStats {
+            enabled,
+            delay_ms: (enabled && delay_blocks != NO_DELAY).then_some(delay_blocks as f32 * LATENCY_MS),
+            erle_db: f32::from_bits(self.erle_db.load(Ordering::Relaxed)),
+            cpu_percent: self.load.take_cpu_percent(),
+            latency_ms: LATENCY_MS,
+        }
+    }
+
+    /// Capture-side canceller for one send track. Create every canceller before calling
+    /// `render_tap`, which takes over the rings registered so far.
+    pub fn canceller(self: &Arc<Self>, stream_id: u8) -> EchoCanceller {
+        let (ring, reference) = HeapRb::<f32>::new(AUDIO_RING_SAMPLES).split();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.retain(|(id, _)| *id != stream_id); // Left over from a session that never played
+            pending.push((stream_id, ring));
+        }
+        EchoCanceller::new(self.clone(), stream_id == 0, reference)
+    }
+
+    /// Render-side handle, owned by the output callback
+    pub fn render_tap(self: &Arc<Self>) -> EchoReference {
+        let rings = self
+            .pending
+            .lock()
+            .map(|mut pending| pending.drain(..).map(|(_, ring)| ring).collect())
+            .unwrap_or_default();
+        EchoReference { control: self.clone(), rings }
+    }
+}
+
+impl Default for EchoControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Owned by the output callback
+pub struct EchoReference {
+    control: Arc<EchoControl>,
+    rings: Vec<HeapProd<f32>>,
+}
+
+impl EchoReference {
+    /// Queue the far-end mix as it is rendered (internal format); dropped while cancellation is off
+    pub fn push(&mut self, samples: &[f32]) {
+        if self.control.enabled.load(Ordering::Relaxed) {
+            for ring in &mut self.rings {
+                ring.push_slice(samples);
+            }
+        }
+    }
+}
+
+// One capture channel's echo path
+struct EchoFilter {
+    w_re: Vec<f32>, // PARTITIONS x BINS
+    w_im: Vec<f32>,
+    echo_spectrum: Vec<f32>,  // Smoothed per-bin power of the echo estimate
+    error_spectrum: Vec<f32>, // And of the output
+    input_power: f32,         // Smoothed, of the capture
+    error_power: f32,         // Smoothed, of the output
+    converged: bool,
+}
+
+impl EchoFilter {
+    fn new() -> Self {
+        Self {
+            w_re: vec![0.0; PARTITIONS * BINS],
+            w_im: vec![0.0; PARTITIONS * BINS],
+            echo_spectrum: vec![0.0; BINS],
+            error_spectrum: vec![0.0; BINS],
+            input_power: 0.0,
+            error_power: 0.0,
+            converged: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.w_re.fill(0.0);
+        self.w_im.fill(0.0);
+        self.echo_spectrum.fill(0.0);
+        self.error_spectrum.fill(0.0);
+        self.input_power = 0.0;
+        self.error_power = 0.0;
+        self.converged = false;
+    }
+}
+
+/// One send track's canceller. The render mix is downmixed to a mono reference, delayed by
+/// the estimated render-to-capture delay and fed to a partitioned-block frequency-domain
+/// NLMS filter per capture channel. Once converged, each bin's step shrinks while its output
+/// is large next to its echo estimate, which holds the filter still through double talk. Output lags input by
+/// `LATENCY_MS`; nothing allocates after construction.
+pub struct EchoCanceller {
+    control: Arc<EchoControl>,
+    reports: bool, // Only the main track publishes delay and ERLE
+    reference: HeapCons<f32>,
+    active: bool,
+    fft: Fft,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    capture: Vec<f32>, // Block being collected, per channel
+    output: Vec<f32>,  // Previous block with the echo removed, per channel
+    fill: usize,
+    scratch: Vec<f32>,      // One interleaved block from the render ring
+    history: Vec<f32>,      // Mono render blocks, ring of HISTORY_BLOCKS
+    history_pos: usize,     // Newest block
+    queue_min: usize,
+    queue_blocks: usize,
+    capture_mono: Vec<f32>,
+    capture_prev: Vec<f32>,
+    render_power: Vec<f32>,
+    capture_power: Vec<f32>,
+    delay: DelayEstimator,
+    applied_delay: usize, // Blocks the filter's reference is delayed by
+    x_re: Vec<f32>,       // Delayed render spectra, ring of PARTITIONS x BINS
+    x_im: Vec<f32>,
+    x_pos: usize,
+    x_power: Vec<f32>, // Per bin, summed over the partitions
+    filters: Vec<EchoFilter>,
+    constrain_next: usize,
+}
+
+impl EchoCanceller {
+    fn new(control: Arc<EchoControl>, reports: bool, reference: HeapCons<f32>) -> Self {
+        Self {
+            control,
+            reports,
+            reference,
+            active: false,
+            fft: Fft::new(FFT_SIZE),
+            re: vec![0.0; FFT_SIZE],
+            im: vec![0.0; FFT_SIZE],
+            capture: vec![0.0; BLOCK * CHANNELS],
+            output: vec![0.0; BLOCK * CHANNELS],
+            fill: 0,
+            scratch: vec![0.0; BLOCK * CHANNELS],
+            history: vec![0.0; HISTORY_BLOCKS * BLOCK],
+            history_pos: 0,
+            queue_min: usize::MAX,
+            queue_blocks: 0,
+            capture_mono: vec![0.0; BLOCK],
+            capture_prev: vec![0.0; BLOCK],
+            render_power: vec![0.0; BINS],
+            capture_power: vec![0.0; BINS],
+            delay: DelayEstimator::new(),
+            applied_delay: 0,
+            x_re: vec![0.0; PARTITIONS * BINS],
+            x_im: vec![0.0; PARTITIONS * BINS],
+            x_pos: 0,
+            x_power: vec![0.0; BINS],
+            filters: (0..CHANNELS).map(|_| EchoFilter::new()).collect(),
+            constrain_next: 0,
+        }
+    }
+
+    /// Start over: nothing learned, nothing queued
+    pub fn reset(&mut self) {
+        self.reference.clear();
+        self.capture.fill(0.0);
+        self.output.fill(0.0);
+        self.fill = 0;
+        self.history.fill(0.0);
+        self.queue_min = usize::MAX;
+        self.queue_blocks = 0;
+        self.capture_prev.fill(0.0);
+        self.delay = DelayEstimator::new();
+        self.applied_delay = 0;
+        self.reset_filters();
+        if self.reports {
+            self.control.delay_blocks.store(NO_DELAY, Ordering::Relaxed);
+            self.control.erle_db.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn reset_filters(&mut self) {
+        self.x_re.fill(0.0);
+        self.x_im.fill(0.0);
+        self.x_power.fill(0.0);
+        self.filters.iter_mut().for_each(EchoFilter::reset);
+    }
+
+    /// Remove the far end's echo from interleaved stereo capture, in place. Passes audio
+    /// through untouched while cancellation is off.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if !self.control.enabled.load(Ordering::Relaxed) {
+            self.active = false;
+            self.reference.clear();
+            return;
+        }
+        if !self.active {
+            self.active = true;
+            self.reset();
+        }
+
+        let start = Instant::now();
+        for frame in samples.chunks_exact_mut(CHANNELS) {
+            for (c, s) in frame.iter_mut().enumerate() {
+                self.capture[c * BLOCK + self.fill] = *s;
+                *s = self.output[c * BLOCK + self.fill];
+            }
+            self.fill += 1;
+            if self.fill == BLOCK {
+                self.fill = 0;
+                self.run_block();
+            }
+        }
+        self.control.load.record(start.elapsed(), samples.len() / CHANNELS);
+    }
+
+    // Next render block into the history, as mono
+    fn pull_reference(&mut self) -> f32 {
+        let got = self.reference.pop_slice(&mut self.scratch);
+        self.scratch[got..].fill(0.0); // Underrun: the far end counts as silent
+
+        // Render and capture clocks drift apart; drop the backlog that never drains
+        self.queue_min = self.queue_min.min(self.reference.occupied_len());
+        self.queue_blocks += 1;
+        if self.queue_blocks >= QUEUE_WINDOW_BLOCKS {
+            if self.queue_min != usize::MAX && self.queue_min > self.scratch.len() {
+                self.reference.skip(self.queue_min - self.scratch.len());
+            }
+            self.queue_min = usize::MAX;
+            self.queue_blocks = 0;
+        }
+
+        self.history_pos = (self.history_pos + 1) % HISTORY_BLOCKS;
+        let pos = self.history_pos;
+        let block = &mut self.history[pos * BLOCK..(pos + 1) * BLOCK];
+        let mut energy = 0.0;
+        for (m, frame) in block.iter_mut().zip(self.scratch.chunks_exact(CHANNELS)) {
+            *m = frame.iter().sum::<f32>() / CHANNELS as f32;
+            energy += *m * *m;
+        }
+        energy / BLOCK as f32
+    }
+
+    fn run_block(&mut self) {
+        let render_active = self.pull_reference() > RENDER_ACTIVE_POWER;
+
+        // Delay: match the newest render against the capture
+        for (i, m) in self.capture_mono.iter_mut().enumerate() {
+            *m = (0..CHANNELS).map(|c| self.capture[c * BLOCK + i]).sum::<f32>() / CHANNELS as f32;
+        }
+        {
+            let Self { fft, re, im, history, history_pos, capture_mono, capture_prev, render_power, capture_power, .. } = self;
+            let prev = history_block(history, *history_pos, 1);
+            let cur = history_block(history, *history_pos, 0);
+            power_spectrum(fft, re, im, prev, cur, render_power);
+            power_spectrum(fft, re, im, capture_prev, capture_mono, capture_power);
+        }
+        self.capture_prev.copy_from_slice(&self.capture_mono);
+        self.delay.update(&self.render_power, &self.capture_power, render_active);
+
+        if let Some(estimate) = self.delay.delay {
+            let aligned = estimate.saturating_sub(DELAY_MARGIN_BLOCKS);
+            if aligned != self.applied_delay {
+                // The learned echo path belongs to the old alignment
+                self.applied_delay = aligned;
+                self.reset_filters();
+            }
+            if self.reports {
+                self.control.delay_blocks.store(estimate as i32, Ordering::Relaxed);
+            }
+        }
+
+        // Delayed render spectrum enters the partition ring
+        self.x_pos = (self.x_pos + 1) % PARTITIONS;
+        {
+            let Self { fft, re, im, history, history_pos, applied_delay, x_re, x_im, x_pos, x_power, .. } = self;
+            re[..BLOCK].copy_from_slice(history_block(history, *history_pos, *applied_delay + 1));
+            re[BLOCK..].copy_from_slice(history_block(history, *history_pos, *applied_delay));
+            im.fill(0.0);
+            fft.forward(re, im);
+            let base = *x_pos * BINS;
+            x_re[base..base + BINS].copy_from_slice(&re[..BINS]);
+            x_im[base..base + BINS].copy_from_slice(&im[..BINS]);
+            x_power.fill(0.0);
+            for (i, (r, m)) in x_re.iter().zip(x_im.iter()).enumerate() {
+                x_power[i % BINS] += r * r + m * m;
+            }
+        }
+
+        for c in 0..CHANNELS {
+            self.filter_channel(c, render_active);
+        }
+        self.constrain_next = (self.constrain_next + 1) % PARTITIONS;
+    }
+
+    fn filter_channel(&mut self, c: usize, render_active: bool) {
+        let Self { control, reports, fft, re, im, capture, output, x_re, x_im, x_pos, x_power, filters, constrain_next, .. } = self;
+        let filter = &mut filters[c];
+        let partition = |p: usize| ((*x_pos + PARTITIONS - p) % PARTITIONS) * BINS;
+
+        // Echo estimate: sum over partitions of W_p * X_(n-p)
+        for k in 0..BINS {
+            let (mut yr, mut yi) = (0.0, 0.0);
+            for p in 0..PARTITIONS {
+                let (w, x) = (p * BINS + k, partition(p) + k);
+                yr += filter.w_re[w] * x_re[x] - filter.w_im[w] * x_im[x];
+                yi += filter.w_re[w] * x_im[x] + filter.w_im[w] * x_re[x];
+            }
+            re[k] = yr;
+            im[k] = yi;
+            // The product spans both halves of the window, the output only the second
+            let power = 0.5 * (yr * yr + yi * yi);
+            filter.echo_spectrum[k] = SPECTRUM_SMOOTHING * filter.echo_spectrum[k] + (1.0 - SPECTRUM_SMOOTHING) * power;
+        }
+        mirror(re, im);
+        fft.inverse(re, im);
+
+        let capture = &capture[c * BLOCK..(c + 1) * BLOCK];
+        let output = &mut output[c * BLOCK..(c + 1) * BLOCK];
+        let (mut error, mut input) = (0.0, 0.0);
+        for i in 0..BLOCK {
+            let e = capture[i] - re[BLOCK + i];
+            output[i] = e;
+            error += e * e;
+            input += capture[i] * capture[i];
+        }
+        let smooth = |avg: f32, x: f32| POWER_SMOOTHING * avg + (1.0 - POWER_SMOOTHING) * x / BLOCK as f32;
+        filter.input_power = smooth(filter.input_power, input);
+        filter.error_power = smooth(filter.error_power, error);
+
+        if !render_active {
+            return;
+        }
+        let erle = filter.input_power / filter.error_power.max(1e-12);
+        filter.converged |= erle > CONVERGED_ERLE;
+        if c == 0 && *reports {
+            control.erle_db.store((10.0 * erle.max(1.0).log10()).to_bits(), Ordering::Relaxed);
+        }
+
+        // NLMS: W_p += mu * E * conj(X_(n-p)) / power
+        re[..BLOCK].fill(0.0);
+        re[BLOCK..].copy_from_slice(output);
+        im.fill(0.0);
+        fft.forward(re, im);
+        let min_ratio = if filter.converged { 0.0 } else { MIN_STEP_RATIO };
+        for k in 0..BINS {
+            let power = re[k] * re[k] + im[k] * im[k];
+            filter.error_spectrum[k] = SPECTRUM_SMOOTHING * filter.error_spectrum[k] + (1.0 - SPECTRUM_SMOOTHING) * power;
+            let ratio = filter.echo_spectrum[k] / (filter.error_spectrum[k] + 1e-12);
+            let g = STEP * (ratio / (1.0 + ratio)).max(min_ratio) / (x_power[k] + REGULARIZATION);
+            for p in 0..PARTITIONS {
+                let x = partition(p) + k;
+                let (xr, xi) = (x_re[x], x_im[x]);
+                filter.w_re[p * BINS + k] += g * (re[k] * xr + im[k] * xi);
+                filter.w_im[p * BINS + k] += g * (im[k] * xr - re[k] * xi);
+            }
+        }
+
+        // Keep one partition a linear (not circular) convolution per block, in turn
+        let w = *constrain_next * BINS;
+        re[..BINS].copy_from_slice(&filter.w_re[w..w + BINS]);
+        im[..BINS].copy_from_slice(&filter.w_im[w..w + BINS]);
+        mirror(re, im);
+        fft.inverse(re, im);
+        re[BLOCK..].fill(0.0);
+        im.fill(0.0);
+        fft.forward(re, im);
+        filter.w_re[w..w + BINS].copy_from_slice(&re[..BINS]);
+        filter.w_im[w..w + BINS].copy_from_slice(&im[..BINS]);
+    }
+}
+
+// Render block `age` blocks before the newest
+fn history_block(history: &[f32], newest: usize, age: usize) -> &[f32] {
+    let index = (newest + HISTORY_BLOCKS - age) % HISTORY_BLOCKS;
+    &history[index * BLOCK..(index + 1) * BLOCK]
+}
+
+// Fill the upper half of a real signal's spectrum from the lower half
+fn mirror(re: &mut [f32], im: &mut [f32]) {
+    for k in BINS..FFT_SIZE {
+        re[k] = re[FFT_SIZE - k];
+        im[k] = -im[FFT_SIZE - k];
+    }
+}
+
+fn power_spectrum(fft: &Fft, re: &mut [f32], im: &mut [f32], prev: &[f32], cur: &[f32], power: &mut [f32]) {
+    re[..BLOCK].copy_from_slice(prev);
+    re[BLOCK..].copy_from_slice(cur);
+    im.fill(0.0);
+    fft.forward(re, im);
+    for (k, p) in power.iter_mut().enumerate() {
+        *p = re[k] * re[k] + im[k] * im[k];
+    }
+}
+
+struct DelayEstimator {
+    render_history: [u32; MAX_DELAY_BLOCKS], // Binary spectra, newest at `pos`
+    pos: usize,
+    render_mean: [f32; BANDS],
+    capture_mean: [f32; BANDS],
+    cost: [f32; MAX_DELAY_BLOCKS], // Smoothed mismatching bits per lag
+    delay: Option<usize>,          // Blocks
+}
+
+impl DelayEstimator {
+    fn new() -> Self {
+        Self {
+            render_history: [0; MAX_DELAY_BLOCKS],
+            pos: 0,
+            render_mean: [0.0; BANDS],
+            capture_mean: [0.0; BANDS],
+            cost: [BANDS as f32 / 2.0; MAX_DELAY_BLOCKS], // Unrelated spectra differ in half the bits
+            delay: None,
+        }
+    }
+
+    fn update(&mut self, render_power: &[f32], capture_power: &[f32], render_active: bool) {
+        let render = binary_spectrum(render_power, &mut self.render_mean);
+        let capture = binary_spectrum(capture_power, &mut self.capture_mean);
+        self.pos = (self.pos + 1) % MAX_DELAY_BLOCKS;
+        self.render_history[self.pos] = render;
+        if !render_active {
+            return;
+        }
+
+        for (lag, cost) in self.cost.iter_mut().enumerate() {
+            let past = self.render_history[(self.pos + MAX_DELAY_BLOCKS - lag) % MAX_DELAY_BLOCKS];
+            *cost += ((capture ^ past).count_ones() as f32 - *cost) * COST_RATE;
+        }
+        let (best, best_cost) = self
+            .cost
+            .iter()
+            .copied()
+            .enumerate()
+            .fold((0, f32::MAX), |best, (lag, cost)| if cost < best.1 { (lag, cost) } else { best });
+        let mean = self.cost.iter().sum::<f32>() / MAX_DELAY_BLOCKS as f32;
+        if best_cost < DELAY_CONFIDENCE * mean {
+            let better = match self.delay {
+                Some(current) => best_cost < self.cost[current] * DELAY_SWITCH_RATIO,
+                None => true,
+            };
+            if better {
+                self.delay = Some(best);
+            }
+        }
+    }
+}
+
+fn binary_spectrum(power: &[f32], mean: &mut [f32; BANDS]) -> u32 {
+    let mut bits = 0u32;
+    for (b, m) in mean.iter_mut().enumerate() {
+        let bin = FIRST_BAND_BIN + 2 * b;
+        let p = power[bin] + power[bin + 1];
+        *m += (p - *m) * BAND_MEAN_RATE;
+        if p > *m {
+            bits |= 1 << b;
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peer::FRAME_SIZE;
+    use std::ops::Range;
+
+    // xorshift64, uniform in [-1, 1)
+    struct Noise(u64);
+
+    impl Noise {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+        }
+    }
+
+    // Speaker-to-microphone path: 60ms of delay and a few reflections of a coloured far
+    // end, with the near end joining for two seconds of double talk
+    #[test]
+    fn echo_canceller_converges_and_holds_through_double_talk() {
+        let sr = SAMPLE_RATE as usize;
+        let n = 12 * sr;
+        let double_talk = 8 * sr..10 * sr;
+        let mut noise = Noise(11);
+        let mut smoothed = 0.0f32;
+        let far: Vec<f32> = (0..n)
+            .map(|_| {
+                smoothed = 0.7 * smoothed + 0.3 * noise.next();
+                0.3 * smoothed
+            })
+            .collect();
+        // White, about -26dBFS
+        let near: Vec<f32> = (0..n)
+            .map(|i| if double_talk.contains(&i) { 0.087 * noise.next() } else { 0.0 })
+            .collect();
+        let taps = [(2880, 0.5f32), (2950, -0.3), (3100, 0.2), (3400, -0.1)];
+        let echo: Vec<f32> = (0..n)
+            .map(|i| taps.iter().filter(|&&(d, _)| i >= d).map(|&(d, g)| g * far[i - d]).sum())
+            .collect();
+
+        let control = Arc::new(EchoControl::new());
+        control.set_enabled(true);
+        let mut canceller = control.canceller(0);
+        let mut reference = control.render_tap();
+        let period = FRAME_SIZE / CHANNELS;
+        let mut out = vec![0.0f32; n];
+        let mut delay_ms = None;
+        for start in (0..n).step_by(period) {
+            let render: Vec<f32> = far[start..start + period].iter().flat_map(|&v| [v; CHANNELS]).collect();
+            reference.push(&render);
+            let mut capture: Vec<f32> = (start..start + period).flat_map(|i| [echo[i] + near[i]; CHANNELS]).collect();
+            canceller.process(&mut capture);
+            for (k, frame) in capture.chunks_exact(CHANNELS).enumerate() {
+                out[start + k] = frame[0];
+            }
+            if start + period == double_talk.start {
+                delay_ms = control.stats().delay_ms;
+            }
+        }
+
+        // The canceller's output lags its input by one block
+        let lag = (LATENCY_MS * SAMPLE_RATE as f32 / 1000.0).round() as usize;
+        let power_db = |range: Range<usize>, f: &dyn Fn(usize) -> f32| {
+            let len = range.len() as f64;
+            10.0 * (range.map(|i| (f(i) as f64).powi(2)).sum::<f64>() / len).max(1e-20).log10()
+        };
+        let delay_ms = delay_ms.expect("delay found");
+        assert!((delay_ms - 60.0).abs() <= 2.0 * LATENCY_MS, "delay {}", delay_ms);
+
+        let erle = |range: Range<usize>| power_db(range.clone(), &|i| echo[i]) - power_db(range, &|i| out[i + lag]);
+        let converged = erle(5 * sr..8 * sr - lag);
+        assert!(converged > 20.0, "ERLE {}", converged);
+
+        let settle = double_talk.start + sr / 2..double_talk.end - lag;
+        let residual = power_db(settle.clone(), &|i| out[i + lag] - near[i]);
+        let near_db = power_db(settle, &|i| near[i]);
+        assert!(residual < near_db - 3.0, "residual {} near {}", residual, near_db);
+
+        let after = erle(double_talk.end + sr..n - lag);
+        assert!(after > 20.0, "ERLE after double talk {}", after);
+    }
+}
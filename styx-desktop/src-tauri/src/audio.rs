@@ -6,8 +6,12 @@ pub struct AudioDevice {
     pub name: String,
     pub is_input: bool,
     pub is_default: bool,
+    pub channels: u16,          // Most channels any supported config offers (0 if unknown)
+    pub sample_rates: Vec<u32>, // Standard rates inside the supported ranges
 }
 
+const STANDARD_SAMPLE_RATES: [u32; 10] = [8000, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioInfo {
     pub hosts: Vec<String>,
@@ -51,20 +55,26 @@ pub fn list_audio_devices() -> Vec<AudioDevice> {
     
     if let Some(device) = host.default_input_device() {
         if let Ok(name) = device.name() {
+            let (channels, sample_rates) = device_capabilities(&device, true);
             devices.push(AudioDevice {
                 name: format!("[기본] {}", name),
                 is_input: true,
                 is_default: true,
+                channels,
+                sample_rates,
             });
         }
     }
     
     if let Some(device) = host.default_output_device() {
         if let Ok(name) = device.name() {
+            let (channels, sample_rates) = device_capabilities(&device, false);
             devices.push(AudioDevice {
                 name: format!("[기본] {}", name),
                 is_input: false,
                 is_default: true,
+                channels,
+                sample_rates,
             });
         }
     }
@@ -73,7 +83,8 @@ pub fn list_audio_devices() -> Vec<AudioDevice> {
         for device in input_devices {
             if let Ok(name) = device.name() {
                 if !devices.iter().any(|d| d.name.contains(&name) && d.is_input) {
-                    devices.push(AudioDevice { name, is_input: true, is_default: false });
+                    let (channels, sample_rates) = device_capabilities(&device, true);
+                    devices.push(AudioDevice { name, is_input: true, is_default: false, channels, sample_rates });
                 }
             }
         }
@@ -83,7 +94,8 @@ pub fn list_audio_devices() -> Vec<AudioDevice> {
         for device in output_devices {
             if let Ok(name) = device.name() {
                 if !devices.iter().any(|d| d.name.contains(&name) && !d.is_input) {
-                    devices.push(AudioDevice { name, is_input: false, is_default: false });
+                    let (channels, sample_rates) = device_capabilities(&device, false);
+                    devices.push(AudioDevice { name, is_input: false, is_default: false, channels, sample_rates });
                 }
             }
         }
@@ -92,6 +104,21 @@ pub fn list_audio_devices() -> Vec<AudioDevice> {
     devices
 }
 
+// 장치 채널 수와 지원 샘플레이트 (입력 채널 라우팅 선택용)
+fn device_capabilities(device: &cpal::Device, is_input: bool) -> (u16, Vec<u32>) {
+    let ranges: Vec<cpal::SupportedStreamConfigRange> = if is_input {
+        device.supported_input_configs().map(|c| c.collect()).unwrap_or_default()
+    } else {
+        device.supported_output_configs().map(|c| c.collect()).unwrap_or_default()
+    };
+    let channels = ranges.iter().map(|r| r.channels()).max().unwrap_or(0);
+    let sample_rates = STANDARD_SAMPLE_RATES
+        .into_iter()
+        .filter(|&rate| ranges.iter().any(|r| (r.min_sample_rate().0..=r.max_sample_rate().0).contains(&rate)))
+        .collect();
+    (channels, sample_rates)
+}
+
 // 오디오 호스트 목록
 pub fn list_audio_hosts() -> Vec<String> {
     cpal::available_hosts()
@@ -0,0 +1,718 @@
+// 페이로드 코덱: 인코더/디코더 트레이트, Opus/PCM 구현, Opus 프로파일과 프리셋
+use audiopus_sys as ffi;
+use serde::{Deserialize, Serialize};
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use crate::lossless;
+use crate::peer::{FrameDuration, CHANNELS, MAX_FRAME_LEN, MAX_PAYLOAD_SIZE, SAMPLE_RATE};
+use crate::stream;
+
+/// Encoded packets this small are DTX frames that need not be transmitted
+const DTX_PACKET_MAX_BYTES: usize = 2;
+
+/// PCM concealment fades to silence over this much consecutive loss
+const PCM_CONCEAL_FADE_MS: f32 = 20.0;
+
+/// Payload format of a stream, chosen per session by the sender and signalled in every
+/// packet header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadCodec {
+    #[default]
+    Opus,
+    Pcm16,    // Uncompressed, no lookahead or coloration (LAN)
+    Pcm24,
+    Lossless, // 24-bit PCM, fixed linear prediction + Rice coding
+}
+
+impl PayloadCodec {
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(PayloadCodec::Opus),
+            1 => Some(PayloadCodec::Pcm16),
+            2 => Some(PayloadCodec::Pcm24),
+            3 => Some(PayloadCodec::Lossless),
+            _ => None,
+        }
+    }
+
+    /// Whether the worst-case payload for a frame fits in one packet (Opus is bounded by
+    /// the encoder's output buffer instead)
+    pub fn fits(self, frame: FrameDuration) -> bool {
+        let frames = frame.samples_per_channel();
+        let max_bytes = match self {
+            PayloadCodec::Opus => 0,
+            PayloadCodec::Pcm16 => frames * CHANNELS * 2,
+            PayloadCodec::Pcm24 => frames * CHANNELS * 3,
+            PayloadCodec::Lossless => lossless::max_payload_bytes(frames),
+        };
+        max_bytes <= MAX_PAYLOAD_SIZE
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpusApplication {
+    Voip,     // Speech-tuned, SILK/hybrid
+    Audio,    // General audio and music
+    LowDelay, // CELT only, no lookahead beyond 2.5ms, no in-band FEC
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BitrateMode {
+    Vbr,
+    Cvbr, // VBR capped at the target, keeps packets within the budget
+    Cbr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bandwidth {
+    Narrowband,    // 4kHz
+    Mediumband,    // 6kHz
+    Wideband,      // 8kHz
+    Superwideband, // 12kHz
+    Fullband,      // 20kHz
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalType {
+    Auto,
+    Voice,
+    Music,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelMode {
+    Mono,   // Downmixed inside the encoder; the stream itself stays stereo
+    Stereo,
+}
+
+/// Everything the encoder can be told, besides the bitrate (per track) and the
+/// expected loss (adaptive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodecProfile {
+    pub application: OpusApplication,
+    pub bitrate_mode: BitrateMode,
+    pub complexity: u8, // 0-10
+    pub max_bandwidth: Bandwidth,
+    pub signal: SignalType,
+    pub dtx: bool, // Silent frames are not transmitted
+    pub fec: bool, // In-band FEC, only effective in SILK and hybrid modes
+    pub channels: ChannelMode,
+}
+
+impl Default for CodecProfile {
+    fn default() -> Self {
+        Self {
+            application: OpusApplication::LowDelay,
+            bitrate_mode: BitrateMode::Cbr, // CBR for consistent latency
+            complexity: 9,                  // libopus default
+            max_bandwidth: Bandwidth::Fullband,
+            signal: SignalType::Auto,
+            dtx: false,
+            fec: true,
+            channels: ChannelMode::Stereo,
+        }
+    }
+}
+
+impl CodecProfile {
+    fn clamped(mut self) -> Self {
+        self.complexity = self.complexity.min(10);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodecPreset {
+    LowestLatency,
+    HighFidelityMusic,
+    VoiceBadNetwork,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CodecPresetInfo {
+    pub preset: CodecPreset,
+    pub profile: CodecProfile,
+    pub min_frame_ms: f32,
+}
+
+impl CodecPreset {
+    pub const ALL: [CodecPreset; 3] = [
+        CodecPreset::LowestLatency,
+        CodecPreset::HighFidelityMusic,
+        CodecPreset::VoiceBadNetwork,
+    ];
+
+    pub fn profile(self) -> CodecProfile {
+        match self {
+            // CELT only: the shortest algorithmic delay Opus has
+            CodecPreset::LowestLatency => CodecProfile {
+                application: OpusApplication::LowDelay,
+                bitrate_mode: BitrateMode::Cbr,
+                complexity: 8,
+                max_bandwidth: Bandwidth::Fullband,
+                signal: SignalType::Music,
+                dtx: false,
+                fec: false,
+                channels: ChannelMode::Stereo,
+            },
+            // Best quality per bit; the extra lookahead costs a few milliseconds
+            CodecPreset::HighFidelityMusic => CodecProfile {
+                application: OpusApplication::Audio,
+                bitrate_mode: BitrateMode::Cvbr,
+                complexity: 10,
+                max_bandwidth: Bandwidth::Fullband,
+                signal: SignalType::Music,
+                dtx: false,
+                fec: false,
+                channels: ChannelMode::Stereo,
+            },
+            // SILK with FEC, mono and wideband so the redundancy fits the bitrate
+            CodecPreset::VoiceBadNetwork => CodecProfile {
+                application: OpusApplication::Voip,
+                bitrate_mode: BitrateMode::Cvbr,
+                complexity: 10,
+                max_bandwidth: Bandwidth::Wideband,
+                signal: SignalType::Voice,
+                dtx: true,
+                fec: true,
+                channels: ChannelMode::Mono,
+            },
+        }
+    }
+
+    /// Shortest frame the preset does what it says at. SILK (and so FEC) needs 10ms or
+    /// longer; below that Opus falls back to CELT.
+    pub fn min_frame(self) -> FrameDuration {
+        match self {
+            CodecPreset::VoiceBadNetwork => FrameDuration::Ms10,
+            CodecPreset::LowestLatency | CodecPreset::HighFidelityMusic => FrameDuration::Ms2_5,
+        }
+    }
+
+    pub fn check_frame(self, frame: FrameDuration) -> Result<(), String> {
+        let min = self.min_frame();
+        if frame.samples_per_channel() < min.samples_per_channel() {
+            return Err(format!("{:?} 프리셋은 {}ms 이상 프레임에서만 동작함 (현재 {}ms)", self, min.ms(), frame.ms()));
+        }
+        Ok(())
+    }
+
+    /// The preset whose profile is exactly this one, if any
+    pub fn matching(profile: &CodecProfile) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.profile() == *profile)
+    }
+
+    pub fn list() -> Vec<CodecPresetInfo> {
+        Self::ALL
+            .iter()
+            .map(|&preset| CodecPresetInfo { preset, profile: preset.profile(), min_frame_ms: preset.min_frame().ms() })
+            .collect()
+    }
+}
+
+/// Session profile shared by the commands and every send loop. Loops compare the
+/// generation once per frame and re-apply the profile when it moved.
+pub struct CodecControl {
+    profile: Mutex<CodecProfile>,
+    generation: AtomicU32,
+}
+
+impl CodecControl {
+    pub fn new() -> Self {
+        Self {
+            profile: Mutex::new(CodecProfile::default()),
+            generation: AtomicU32::new(0),
+        }
+    }
+
+    pub fn set_profile(&self, profile: CodecProfile) {
+        if let Ok(mut current) = self.profile.lock() {
+            *current = profile.clamped();
+        }
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn profile(&self) -> CodecProfile {
+        self.profile.lock().map(|p| *p).unwrap_or_default()
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation.load(Ordering::Acquire)
+    }
+}
+
+impl Default for CodecControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sending half of a payload codec. One instance per send track, owned by its send loop.
+pub trait AudioCodec: Send {
+    fn codec(&self) -> PayloadCodec;
+
+    /// Whether a frame of this length fits in one packet
+    fn supports_frame(&self, frame: FrameDuration) -> bool {
+        self.codec().fits(frame)
+    }
+
+    /// Encode one frame of interleaved stereo
+    fn encode(&mut self, samples: &[f32]) -> Result<Vec<u8>, String>;
+
+    /// Payloads the codec marks as not worth sending (DTX)
+    fn is_silent_packet(&self, _payload: &[u8]) -> bool {
+        false
+    }
+
+    // Parameters; codecs without them accept and ignore the call
+    fn apply_profile(&mut self, _profile: &CodecProfile) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn set_bitrate_kbps(&mut self, _bitrate_kbps: u32) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Expected loss, for codecs that carry redundancy
+    fn set_packet_loss_perc(&mut self, _loss_perc: u32) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Receiving half of a payload codec. One instance per sender, matching the codec its
+/// packets carry.
+pub trait AudioDecoder: Send {
+    fn codec(&self) -> PayloadCodec;
+
+    fn decode(&mut self, payload: &[u8], frame: FrameDuration) -> Result<Vec<f32>, String>;
+
+    /// Stand-in for a frame that never arrived
+    fn conceal(&mut self, frame: FrameDuration) -> Result<Vec<f32>, String>;
+
+    /// Rebuild the frame before `payload` from redundancy carried in it, if the codec has any
+    fn decode_fec(&mut self, _payload: &[u8], _frame: FrameDuration) -> Option<Vec<f32>> {
+        None
+    }
+}
+
+impl PayloadCodec {
+    pub fn encoder(self, profile: &CodecProfile, bitrate_kbps: u32) -> Result<Box<dyn AudioCodec>, String> {
+        Ok(match self {
+            PayloadCodec::Opus => Box::new(OpusEncoder::new(profile, bitrate_kbps)?),
+            _ => Box::new(PcmEncoder { codec: self }),
+        })
+    }
+
+    pub fn decoder(self) -> Result<Box<dyn AudioDecoder>, String> {
+        Ok(match self {
+            PayloadCodec::Opus => Box::new(OpusDecoder::new()?),
+            _ => Box::new(PcmDecoder { codec: self, last_frame: Vec::new(), conceal_gain: 1.0 }),
+        })
+    }
+}
+
+/// 48kHz stereo Opus encoder with the full CTL set (the opus crate only wraps a few)
+pub struct OpusEncoder {
+    ptr: *mut ffi::OpusEncoder,
+    application: OpusApplication,
+    bitrate_kbps: u32,
+    loss_perc: u32,
+}
+
+// The state is only ever used by the thread that owns the encoder
+unsafe impl Send for OpusEncoder {}
+
+impl OpusEncoder {
+    pub fn new(profile: &CodecProfile, bitrate_kbps: u32) -> Result<Self, String> {
+        let mut encoder = Self {
+            ptr: create_state(profile.application)?,
+            application: profile.application,
+            bitrate_kbps,
+            loss_perc: 0,
+        };
+        encoder.set_bitrate_kbps(bitrate_kbps)?;
+        encoder.configure(profile)?;
+        Ok(encoder)
+    }
+
+    fn configure(&mut self, profile: &CodecProfile) -> Result<(), String> {
+        let (vbr, constrained) = match profile.bitrate_mode {
+            BitrateMode::Vbr => (1, 0),
+            BitrateMode::Cvbr => (1, 1),
+            BitrateMode::Cbr => (0, 0),
+        };
+        self.ctl(ffi::OPUS_SET_VBR_REQUEST, vbr)?;
+        self.ctl(ffi::OPUS_SET_VBR_CONSTRAINT_REQUEST, constrained)?;
+        self.ctl(ffi::OPUS_SET_COMPLEXITY_REQUEST, profile.complexity.min(10) as i32)?;
+        self.ctl(ffi::OPUS_SET_MAX_BANDWIDTH_REQUEST, match profile.max_bandwidth {
+            Bandwidth::Narrowband => ffi::OPUS_BANDWIDTH_NARROWBAND,
+            Bandwidth::Mediumband => ffi::OPUS_BANDWIDTH_MEDIUMBAND,
+            Bandwidth::Wideband => ffi::OPUS_BANDWIDTH_WIDEBAND,
+            Bandwidth::Superwideband => ffi::OPUS_BANDWIDTH_SUPERWIDEBAND,
+            Bandwidth::Fullband => ffi::OPUS_BANDWIDTH_FULLBAND,
+        })?;
+        self.ctl(ffi::OPUS_SET_SIGNAL_REQUEST, match profile.signal {
+            SignalType::Auto => ffi::OPUS_AUTO,
+            SignalType::Voice => ffi::OPUS_SIGNAL_VOICE,
+            SignalType::Music => ffi::OPUS_SIGNAL_MUSIC,
+        })?;
+        self.ctl(ffi::OPUS_SET_DTX_REQUEST, profile.dtx as i32)?;
+        self.ctl(ffi::OPUS_SET_INBAND_FEC_REQUEST, profile.fec as i32)?;
+        self.ctl(ffi::OPUS_SET_FORCE_CHANNELS_REQUEST, match profile.channels {
+            ChannelMode::Mono => 1,
+            ChannelMode::Stereo => ffi::OPUS_AUTO,
+        })
+    }
+
+    fn ctl(&mut self, request: c_int, value: i32) -> Result<(), String> {
+        check(unsafe { ffi::opus_encoder_ctl(self.ptr, request, value) }).map(|_| ())
+    }
+}
+
+impl AudioCodec for OpusEncoder {
+    fn codec(&self) -> PayloadCodec {
+        PayloadCodec::Opus
+    }
+
+    fn encode(&mut self, samples: &[f32]) -> Result<Vec<u8>, String> {
+        let mut output = vec![0u8; MAX_PAYLOAD_SIZE];
+        let len = unsafe {
+            ffi::opus_encode_float(
+                self.ptr,
+                samples.as_ptr(),
+                (samples.len() / CHANNELS) as c_int,
+                output.as_mut_ptr(),
+                output.len() as ffi::opus_int32,
+            )
+        };
+        let len = check(len).map_err(|e| format!("인코딩 실패: {}", e))?;
+        output.truncate(len as usize);
+        Ok(output)
+    }
+
+    fn is_silent_packet(&self, payload: &[u8]) -> bool {
+        payload.len() <= DTX_PACKET_MAX_BYTES
+    }
+
+    /// libopus fixes the application after the first frame, so changing it starts a
+    /// fresh encoder state
+    fn apply_profile(&mut self, profile: &CodecProfile) -> Result<(), String> {
+        if profile.application != self.application {
+            let ptr = create_state(profile.application)?;
+            unsafe { ffi::opus_encoder_destroy(self.ptr) };
+            self.ptr = ptr;
+            self.application = profile.application;
+            self.set_bitrate_kbps(self.bitrate_kbps)?;
+            self.set_packet_loss_perc(self.loss_perc)?;
+        }
+        self.configure(profile)
+    }
+
+    fn set_bitrate_kbps(&mut self, bitrate_kbps: u32) -> Result<(), String> {
+        self.bitrate_kbps = bitrate_kbps;
+        self.ctl(ffi::OPUS_SET_BITRATE_REQUEST, bitrate_kbps as i32 * 1000)
+    }
+
+    /// Sizes the in-band FEC
+    fn set_packet_loss_perc(&mut self, loss_perc: u32) -> Result<(), String> {
+        self.loss_perc = loss_perc;
+        self.ctl(ffi::OPUS_SET_PACKET_LOSS_PERC_REQUEST, loss_perc as i32)
+    }
+}
+
+impl Drop for OpusEncoder {
+    fn drop(&mut self) {
+        unsafe { ffi::opus_encoder_destroy(self.ptr) };
+    }
+}
+
+fn create_state(application: OpusApplication) -> Result<*mut ffi::OpusEncoder, String> {
+    let application = match application {
+        OpusApplication::Voip => ffi::OPUS_APPLICATION_VOIP,
+        OpusApplication::Audio => ffi::OPUS_APPLICATION_AUDIO,
+        OpusApplication::LowDelay => ffi::OPUS_APPLICATION_RESTRICTED_LOWDELAY,
+    };
+    let mut error: c_int = 0;
+    let ptr = unsafe { ffi::opus_encoder_create(SAMPLE_RATE as ffi::opus_int32, CHANNELS as c_int, application, &mut error) };
+    if error != ffi::OPUS_OK || ptr.is_null() {
+        return Err(format!("Opus 인코더 생성 실패: {}", error));
+    }
+    Ok(ptr)
+}
+
+fn check(code: c_int) -> Result<c_int, String> {
+    if code < 0 {
+        Err(format!("Opus 오류: {}", code))
+    } else {
+        Ok(code)
+    }
+}
+
+pub struct OpusDecoder {
+    decoder: opus::Decoder,
+}
+
+impl OpusDecoder {
+    pub fn new() -> Result<Self, String> {
+        let decoder = opus::Decoder::new(SAMPLE_RATE, opus::Channels::Stereo)
+            .map_err(|e| format!("Opus 디코더 생성 실패: {:?}", e))?;
+        Ok(Self { decoder })
+    }
+}
+
+impl AudioDecoder for OpusDecoder {
+    fn codec(&self) -> PayloadCodec {
+        PayloadCodec::Opus
+    }
+
+    fn decode(&mut self, payload: &[u8], _frame: FrameDuration) -> Result<Vec<f32>, String> {
+        let mut pcm = vec![0f32; MAX_FRAME_LEN];
+        let len = self.decoder.decode_float(payload, &mut pcm, false)
+            .map_err(|e| format!("디코딩 실패: {:?}", e))?;
+        pcm.truncate(len * CHANNELS); // len is samples per channel
+        Ok(pcm)
+    }
+
+    /// Opus built-in PLC
+    fn conceal(&mut self, frame: FrameDuration) -> Result<Vec<f32>, String> {
+        let mut pcm = vec![0f32; frame.frame_len()];
+        let len = self.decoder.decode_float(&[], &mut pcm, true)
+            .map_err(|e| format!("PLC 실패: {:?}", e))?;
+        pcm.truncate(len * CHANNELS);
+        Ok(pcm)
+    }
+
+    /// In-band FEC (LBRR). Opus needs the lost frame's exact length, taken to be the
+    /// same as this packet's. libopus answers a packet without LBRR with plain PLC and
+    /// still reports success, so the packet is checked first.
+    fn decode_fec(&mut self, payload: &[u8], frame: FrameDuration) -> Option<Vec<f32>> {
+        if !has_lbrr(payload) {
+            return None;
+        }
+        let mut pcm = vec![0f32; frame.frame_len()];
+        let len = self.decoder.decode_float(payload, &mut pcm, true).ok()?;
+        pcm.truncate(len * CHANNELS);
+        Some(pcm)
+    }
+}
+
+// Whether an Opus packet carries LBRR for the frame before it. Only SILK and hybrid packets
+// (TOC config < 16) can; the flag follows each SILK channel's VAD flags at the start of the
+// range-coded first frame. Multi-frame packets with explicit lengths are not inspected.
+fn has_lbrr(packet: &[u8]) -> bool {
+    let Some(&toc) = packet.first() else { return false };
+    let config = toc >> 3;
+    if config >= 16 || toc & 0x3 > 1 {
+        return false;
+    }
+    // SILK frames of 40 and 60ms are coded as two and three 20ms frames
+    let frames_per_packet = if config < 12 { (config % 4).max(1) as usize } else { 1 };
+    let silk_channels = if toc & 0x4 != 0 { 2 } else { 1 };
+    let mut dec = RangeDecoder::new(&packet[1..]);
+    for _ in 0..silk_channels {
+        for _ in 0..frames_per_packet {
+            dec.bit(); // VAD
+        }
+        if dec.bit() {
+            return true;
+        }
+    }
+    false
+}
+
+// The start of the libopus range decoder (ec_dec), enough to read equiprobable flags
+struct RangeDecoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    rng: u32,
+    val: u32,
+    rem: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        let mut dec = Self { buf, pos: 0, rng: 1 << 7, val: 0, rem: 0 };
+        dec.rem = dec.read_byte();
+        dec.val = dec.rng - 1 - (dec.rem >> 1);
+        dec.normalize();
+        dec
+    }
+
+    fn read_byte(&mut self) -> u32 {
+        let byte = self.buf.get(self.pos).copied().unwrap_or(0);
+        self.pos += 1;
+        byte as u32
+    }
+
+    fn normalize(&mut self) {
+        while self.rng <= 1 << 23 {
+            self.rng <<= 8;
+            let sym = self.rem;
+            self.rem = self.read_byte();
+            let sym = (sym << 8 | self.rem) >> 1;
+            self.val = ((self.val << 8) + (0xFF & !sym)) & 0x7FFF_FFFF;
+        }
+    }
+
+    // ec_dec_bit_logp(dec, 1)
+    fn bit(&mut self) -> bool {
+        let s = self.rng >> 1;
+        let one = self.val < s;
+        if one {
+            self.rng = s;
+        } else {
+            self.val -= s;
+            self.rng -= s;
+        }
+        self.normalize();
+        one
+    }
+}
+
+// PCM16/PCM24/무손실: 상태 없는 인코더
+struct PcmEncoder {
+    codec: PayloadCodec,
+}
+
+impl AudioCodec for PcmEncoder {
+    fn codec(&self) -> PayloadCodec {
+        self.codec
+    }
+
+    fn encode(&mut self, samples: &[f32]) -> Result<Vec<u8>, String> {
+        Ok(match self.codec {
+            PayloadCodec::Pcm16 => stream::samples_to_pcm(samples, 16),
+            PayloadCodec::Lossless => lossless::encode(samples),
+            _ => stream::samples_to_pcm(samples, 24),
+        })
+    }
+}
+
+struct PcmDecoder {
+    codec: PayloadCodec,
+    last_frame: Vec<f32>, // Last frame decoded or repeated, before the fade; the source for concealment
+    conceal_gain: f32,    // Falls across consecutive concealments, back to 1 on a decoded frame
+}
+
+impl AudioDecoder for PcmDecoder {
+    fn codec(&self) -> PayloadCodec {
+        self.codec
+    }
+
+    fn decode(&mut self, payload: &[u8], frame: FrameDuration) -> Result<Vec<f32>, String> {
+        let samples = match self.codec {
+            PayloadCodec::Pcm16 => stream::pcm_to_samples(payload, 16),
+            PayloadCodec::Lossless => lossless::decode(payload, frame.samples_per_channel())?,
+            _ => stream::pcm_to_samples(payload, 24),
+        };
+        if samples.len() != frame.frame_len() {
+            return Err(format!("페이로드 길이 불일치: {} bytes", payload.len()));
+        }
+        self.last_frame.clone_from(&samples);
+        self.conceal_gain = 1.0;
+        Ok(samples)
+    }
+
+    // 직전 프레임을 시간 반전해 반복 (이음매에서 파형이 끊기지 않음), 연속 손실이면 점점 줄여 무음으로
+    fn conceal(&mut self, frame: FrameDuration) -> Result<Vec<f32>, String> {
+        let mut samples = vec![0.0f32; frame.frame_len()];
+        if self.last_frame.len() == samples.len() {
+            for (out, prev) in samples.chunks_exact_mut(CHANNELS).zip(self.last_frame.chunks_exact(CHANNELS).rev()) {
+                out.copy_from_slice(prev);
+            }
+        }
+        self.last_frame.clone_from(&samples);
+
+        // Linear ramp within the frame, continuing from where the previous concealment ended
+        let start = self.conceal_gain;
+        let end = (start - frame.ms() / PCM_CONCEAL_FADE_MS).max(0.0);
+        let step = (end - start) / frame.samples_per_channel() as f32;
+        for (i, out) in samples.chunks_exact_mut(CHANNELS).enumerate() {
+            let gain = start + step * (i + 1) as f32;
+            out.iter_mut().for_each(|s| *s *= gain);
+        }
+        self.conceal_gain = end;
+        Ok(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const FRAME: FrameDuration = FrameDuration::Ms5;
+
+    fn tone(freq_hz: f32, amplitude: f32) -> Vec<f32> {
+        (0..FRAME.frame_len())
+            .map(|i| amplitude * (2.0 * PI * freq_hz * (i / CHANNELS) as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    fn encode(codec: PayloadCodec, input: &[f32]) -> Vec<u8> {
+        codec.encoder(&CodecProfile::default(), 0).expect("encoder").encode(input).expect("encode")
+    }
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0f32, |m, s| m.max(s.abs()))
+    }
+
+    #[test]
+    fn pcm_round_trip_is_within_one_step() {
+        let input = tone(997.0, 0.9);
+        for (codec, bits) in [(PayloadCodec::Pcm16, 16), (PayloadCodec::Pcm24, 24)] {
+            let payload = encode(codec, &input);
+            assert_eq!(payload.len(), FRAME.frame_len() * bits / 8);
+            let output = codec.decoder().expect("decoder").decode(&payload, FRAME).expect("decode");
+            let step = 1.0 / ((1u32 << (bits - 1)) - 1) as f32;
+            let worst = input.iter().zip(&output).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+            assert!(worst <= step / 2.0 + f32::EPSILON, "{:?} error {}", codec, worst);
+        }
+    }
+
+    #[test]
+    fn short_payloads_are_rejected() {
+        let input = tone(440.0, 0.5);
+        for codec in [PayloadCodec::Pcm16, PayloadCodec::Pcm24, PayloadCodec::Lossless] {
+            let payload = encode(codec, &input);
+            let mut decoder = codec.decoder().expect("decoder");
+            assert!(decoder.decode(&[], FRAME).is_err(), "{:?} empty", codec);
+            assert!(decoder.decode(&payload[..payload.len() / 2], FRAME).is_err(), "{:?} truncated", codec);
+            // A frame of another length is not this one
+            assert!(decoder.decode(&payload, FrameDuration::Ms10).is_err(), "{:?} wrong frame", codec);
+        }
+    }
+
+    #[test]
+    fn pcm_concealment_fades_out() {
+        let input = tone(440.0, 0.5);
+        let payload = encode(PayloadCodec::Pcm24, &input);
+        let mut decoder = PayloadCodec::Pcm24.decoder().expect("decoder");
+        decoder.decode(&payload, FRAME).expect("decode");
+
+        let mut last = peak(&input);
+        for n in 0..10 {
+            let level = peak(&decoder.conceal(FRAME).expect("conceal"));
+            assert!(level < last || level == 0.0, "concealment {} did not fade: {} after {}", n, level, last);
+            last = level;
+        }
+        assert_eq!(last, 0.0);
+
+        // A decoded frame restores full level
+        let decoded = decoder.decode(&payload, FRAME).expect("decode");
+        assert_eq!(peak(&decoded), peak(&input));
+    }
+}
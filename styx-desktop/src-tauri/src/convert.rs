@@ -0,0 +1,428 @@
+// 장치 포맷 변환 (샘플 포맷/샘플레이트/채널 수 ↔ 내부 48kHz f32 인터리브드 스테레오)
+use cpal::traits::DeviceTrait;
+use cpal::{FromSample, SampleFormat, SampleRate, SizedSample, SupportedStreamConfig, SupportedStreamConfigRange};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+use crate::peer::{CHANNELS, FRAME_SIZE, SAMPLE_RATE};
+use crate::resample::RateConverter;
+
+const MAX_CALLBACK_FRAMES: usize = 8192; // Larger device buffers are converted in pieces of this size
+const CENTER_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2; // -3 dB for centre and surround channels
+const MAX_INPUT_CHANNELS: u32 = 256; // Bound on routed channel numbers; beyond any real interface
+
+#[derive(Clone, Copy)]
+enum Speaker {
+    Left,
+    Right,
+    Center,
+    Lfe,
+    LeftSurround,
+    RightSurround,
+}
+
+// Common channel orders (WAVE/SMPTE) by count; cpal reports no channel map
+fn speaker_layout(channels: usize) -> Option<&'static [Speaker]> {
+    use Speaker::*;
+    match channels {
+        3 => Some(&[Left, Right, Center]),
+        4 => Some(&[Left, Right, LeftSurround, RightSurround]),
+        5 => Some(&[Left, Right, Center, LeftSurround, RightSurround]),
+        6 => Some(&[Left, Right, Center, Lfe, LeftSurround, RightSurround]),
+        8 => Some(&[Left, Right, Center, Lfe, LeftSurround, RightSurround, LeftSurround, RightSurround]),
+        _ => None,
+    }
+}
+
+/// Which hardware input channels feed the sent stream (0-based)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum InputRouting {
+    #[default]
+    Auto,                              // Downmix the device layout
+    Mono { channel: u16 },             // One channel to both sides
+    Stereo { left: u16, right: u16 },
+}
+
+impl InputRouting {
+    /// Device channels the routing needs
+    pub fn min_channels(&self) -> u32 {
+        match *self {
+            InputRouting::Auto => 1,
+            InputRouting::Mono { channel } => channel as u32 + 1,
+            InputRouting::Stereo { left, right } => left.max(right) as u32 + 1,
+        }
+    }
+
+    /// Reject channel numbers no device can have, before they are stored
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_channels() > MAX_INPUT_CHANNELS {
+            return Err(format!("입력 채널 번호는 0-{} 범위여야 합니다", MAX_INPUT_CHANNELS - 1));
+        }
+        Ok(())
+    }
+}
+
+/// (left, right) weight of every device channel relative to internal stereo
+struct ChannelMatrix {
+    rows: Vec<[f32; CHANNELS]>,
+}
+
+impl ChannelMatrix {
+    /// Device channels into stereo
+    fn downmix(channels: usize) -> Self {
+        let mut rows: Vec<[f32; CHANNELS]> = match channels {
+            1 => vec![[1.0, 1.0]],
+            2 => vec![[1.0, 0.0], [0.0, 1.0]],
+            n => match speaker_layout(n) {
+                Some(layout) => layout
+                    .iter()
+                    .map(|speaker| match speaker {
+                        Speaker::Left => [1.0, 0.0],
+                        Speaker::Right => [0.0, 1.0],
+                        Speaker::Center => [CENTER_GAIN, CENTER_GAIN],
+                        Speaker::Lfe => [0.0, 0.0],
+                        Speaker::LeftSurround => [CENTER_GAIN, 0.0],
+                        Speaker::RightSurround => [0.0, CENTER_GAIN],
+                    })
+                    .collect(),
+                // Unknown layout: even channels left, odd channels right
+                None => (0..n).map(|c| if c % 2 == 0 { [1.0, 0.0] } else { [0.0, 1.0] }).collect(),
+            },
+        };
+        // Full scale on every channel stays full scale after the sum
+        if channels > 2 {
+            for side in 0..CHANNELS {
+                let total: f32 = rows.iter().map(|r| r[side]).sum();
+                if total > 0.0 {
+                    rows.iter_mut().for_each(|r| r[side] /= total);
+                }
+            }
+        }
+        Self { rows }
+    }
+
+    /// Selected hardware channels into stereo; `channels` must cover the routing
+    fn route(routing: InputRouting, channels: usize) -> Self {
+        let mut rows = vec![[0.0; CHANNELS]; channels];
+        match routing {
+            InputRouting::Auto => return Self::downmix(channels),
+            InputRouting::Mono { channel } => rows[channel as usize] = [1.0, 1.0],
+            InputRouting::Stereo { left, right } => {
+                rows[left as usize][0] = 1.0;
+                rows[right as usize][1] = 1.0;
+            }
+        }
+        Self { rows }
+    }
+
+    /// Stereo onto device channels: mono gets the average, extra channels stay silent
+    fn upmix(channels: usize) -> Self {
+        let rows = match channels {
+            1 => vec![[0.5, 0.5]],
+            n => (0..n)
+                .map(|c| match c {
+                    0 => [1.0, 0.0],
+                    1 => [0.0, 1.0],
+                    _ => [0.0, 0.0],
+                })
+                .collect(),
+        };
+        Self { rows }
+    }
+
+    fn is_identity(&self) -> bool {
+        self.rows == [[1.0, 0.0], [0.0, 1.0]]
+    }
+}
+
+/// Capture side: device samples to the internal format the encoder expects.
+/// All buffers are sized up front; converting never allocates.
+pub struct InputConverter {
+    matrix: ChannelMatrix,
+    resampler: Option<RateConverter>,
+    stereo: Vec<f32>,
+    resampled: Vec<f32>,
+}
+
+impl InputConverter {
+    pub fn new(config: &cpal::StreamConfig, routing: InputRouting) -> Result<Self, String> {
+        routing.validate()?;
+        if routing.min_channels() > config.channels as u32 {
+            return Err(format!("입력 채널 {} 없음 (장치 채널 수 {})", routing.min_channels(), config.channels));
+        }
+        let rate = config.sample_rate.0;
+        // Frames one full piece can turn into at 48 kHz, plus the resampler's rounding
+        let resampled_frames = MAX_CALLBACK_FRAMES * SAMPLE_RATE as usize / rate.max(1) as usize + 2;
+        Ok(Self {
+            matrix: ChannelMatrix::route(routing, config.channels as usize),
+            resampler: (rate != SAMPLE_RATE).then(|| RateConverter::new(rate, SAMPLE_RATE, CHANNELS)),
+            stereo: Vec::with_capacity(MAX_CALLBACK_FRAMES * CHANNELS),
+            resampled: Vec::with_capacity(if rate != SAMPLE_RATE { resampled_frames * CHANNELS } else { 0 }),
+        })
+    }
+
+    /// Convert one capture callback. `sink` receives the result in the internal format,
+    /// possibly in several pieces and with a different number of frames than `data`.
+    pub fn process(&mut self, data: &[f32], mut sink: impl FnMut(&[f32])) {
+        for piece in data.chunks(MAX_CALLBACK_FRAMES * self.matrix.rows.len()) {
+            self.stereo.clear();
+            downmix_into(&self.matrix, piece, &mut self.stereo);
+            match self.resampler.as_mut() {
+                None => sink(&self.stereo),
+                Some(resampler) => {
+                    self.resampled.clear();
+                    resampler.process(&self.stereo, &mut self.resampled);
+                    sink(&self.resampled);
+                }
+            }
+        }
+    }
+}
+
+fn downmix_into(matrix: &ChannelMatrix, data: &[f32], out: &mut Vec<f32>) {
+    if matrix.is_identity() {
+        out.extend_from_slice(data);
+        return;
+    }
+    for frame in data.chunks_exact(matrix.rows.len()) {
+        let mut mixed = [0.0f32; CHANNELS];
+        for (s, row) in frame.iter().zip(&matrix.rows) {
+            for (m, w) in mixed.iter_mut().zip(row) {
+                *m += s * w;
+            }
+        }
+        out.extend_from_slice(&mixed);
+    }
+}
+
+/// Playback side: pulls internal-format audio and writes it in the device's format.
+/// Like `InputConverter`, it never allocates once created.
+pub struct OutputConverter {
+    matrix: ChannelMatrix,
+    resampler: Option<RateConverter>,
+    block: [f32; FRAME_SIZE],
+    pending: Vec<f32>, // Internal channels at the device rate, not yet written
+}
+
+impl OutputConverter {
+    pub fn new(config: &cpal::StreamConfig) -> Self {
+        let rate = config.sample_rate.0;
+        Self {
+            matrix: ChannelMatrix::upmix(config.channels as usize),
+            resampler: (rate != SAMPLE_RATE).then(|| RateConverter::new(SAMPLE_RATE, rate, CHANNELS)),
+            block: [0.0; FRAME_SIZE],
+            // One piece plus the part of a converted block left over (up to 192 kHz)
+            pending: Vec::with_capacity((MAX_CALLBACK_FRAMES + 4 * FRAME_SIZE) * CHANNELS),
+        }
+    }
+
+    /// Fill a device buffer. `source` fills a slice with 48 kHz interleaved stereo; with
+    /// resampling it is called once per `FRAME_SIZE` block until enough is converted.
+    pub fn fill(&mut self, data: &mut [f32], mut source: impl FnMut(&mut [f32])) {
+        let piece_len = MAX_CALLBACK_FRAMES * self.matrix.rows.len();
+        for piece in data.chunks_mut(piece_len) {
+            self.fill_piece(piece, &mut source);
+        }
+    }
+
+    fn fill_piece(&mut self, data: &mut [f32], source: &mut impl FnMut(&mut [f32])) {
+        let needed = data.len() / self.matrix.rows.len() * CHANNELS;
+        match self.resampler.as_mut() {
+            None => {
+                self.pending.resize(needed, 0.0);
+                source(&mut self.pending);
+            }
+            Some(resampler) => {
+                while self.pending.len() < needed {
+                    source(&mut self.block);
+                    resampler.process(&self.block, &mut self.pending);
+                }
+            }
+        }
+
+        if self.matrix.is_identity() {
+            data.copy_from_slice(&self.pending[..needed]);
+        } else {
+            for (frame, stereo) in data.chunks_exact_mut(self.matrix.rows.len()).zip(self.pending.chunks_exact(CHANNELS)) {
+                for (sample, row) in frame.iter_mut().zip(&self.matrix.rows) {
+                    *sample = row[0] * stereo[0] + row[1] * stereo[1];
+                }
+            }
+        }
+        self.pending.drain(..needed);
+    }
+}
+
+/// Input config closest to the internal format with at least the channels `routing` uses
+pub fn choose_input_config(device: &cpal::Device, routing: InputRouting) -> Result<SupportedStreamConfig, String> {
+    let min_channels = routing.min_channels();
+    let ranges: Vec<_> = device
+        .supported_input_configs()
+        .map_err(|e| e.to_string())?
+        .filter(|r| r.channels() as u32 >= min_channels)
+        .collect();
+    let default = device.default_input_config().ok().filter(|c| c.channels() as u32 >= min_channels);
+    choose_config(ranges, default)
+}
+
+/// Output config closest to the internal format, chosen from what the device supports
+pub fn choose_output_config(device: &cpal::Device) -> Result<SupportedStreamConfig, String> {
+    let ranges: Vec<_> = device.supported_output_configs().map_err(|e| e.to_string())?.collect();
+    choose_config(ranges, device.default_output_config().ok())
+}
+
+// Prefer 48 kHz (no resampling), then the nearest rate; stereo, then more channels, then
+// mono; f32, then the widest sample format
+fn choose_config(
+    ranges: Vec<SupportedStreamConfigRange>,
+    default: Option<SupportedStreamConfig>,
+) -> Result<SupportedStreamConfig, String> {
+    ranges
+        .into_iter()
+        .filter(|r| is_supported_format(r.sample_format()))
+        .map(|r| {
+            let rate = SAMPLE_RATE.clamp(r.min_sample_rate().0, r.max_sample_rate().0);
+            let channel_rank = match r.channels() {
+                2 => 0,
+                1 => u16::MAX,
+                n => n,
+            };
+            let format = r.sample_format();
+            let format_rank = (format != SampleFormat::F32, Reverse(format.sample_size()));
+            ((rate.abs_diff(SAMPLE_RATE), channel_rank, format_rank), r.with_sample_rate(SampleRate(rate)))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, config)| config)
+        .or(default)
+        .ok_or_else(|| "사용 가능한 오디오 설정 없음".to_string())
+}
+
+fn is_supported_format(format: SampleFormat) -> bool {
+    matches!(
+        format,
+        SampleFormat::I8
+            | SampleFormat::I16
+            | SampleFormat::I32
+            | SampleFormat::I64
+            | SampleFormat::U8
+            | SampleFormat::U16
+            | SampleFormat::U32
+            | SampleFormat::U64
+            | SampleFormat::F32
+            | SampleFormat::F64
+    )
+}
+
+/// Input stream in the device's native sample format; `callback` always receives f32
+pub fn build_input_stream<D, E>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    format: SampleFormat,
+    mut callback: D,
+    error_callback: E,
+) -> Result<cpal::Stream, String>
+where
+    D: FnMut(&[f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Send + 'static,
+{
+    let stream = match format {
+        SampleFormat::I8 => input_stream::<i8, _, _>(device, config, callback, error_callback),
+        SampleFormat::I16 => input_stream::<i16, _, _>(device, config, callback, error_callback),
+        SampleFormat::I32 => input_stream::<i32, _, _>(device, config, callback, error_callback),
+        SampleFormat::I64 => input_stream::<i64, _, _>(device, config, callback, error_callback),
+        SampleFormat::U8 => input_stream::<u8, _, _>(device, config, callback, error_callback),
+        SampleFormat::U16 => input_stream::<u16, _, _>(device, config, callback, error_callback),
+        SampleFormat::U32 => input_stream::<u32, _, _>(device, config, callback, error_callback),
+        SampleFormat::U64 => input_stream::<u64, _, _>(device, config, callback, error_callback),
+        SampleFormat::F32 => device.build_input_stream(config, move |data: &[f32], _| callback(data), error_callback, None),
+        SampleFormat::F64 => input_stream::<f64, _, _>(device, config, callback, error_callback),
+        other => return Err(format!("지원하지 않는 샘플 포맷: {}", other)),
+    };
+    stream.map_err(|e| e.to_string())
+}
+
+fn input_stream<T, D, E>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut callback: D,
+    error_callback: E,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+    D: FnMut(&[f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Send + 'static,
+{
+    let piece_len = MAX_CALLBACK_FRAMES * config.channels as usize;
+    let mut scratch: Vec<f32> = Vec::with_capacity(piece_len);
+    device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            for piece in data.chunks(piece_len) {
+                scratch.clear();
+                scratch.extend(piece.iter().map(|&s| s.to_sample::<f32>()));
+                callback(&scratch);
+            }
+        },
+        error_callback,
+        None,
+    )
+}
+
+/// Output stream in the device's native sample format; `callback` always fills f32
+pub fn build_output_stream<D, E>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    format: SampleFormat,
+    mut callback: D,
+    error_callback: E,
+) -> Result<cpal::Stream, String>
+where
+    D: FnMut(&mut [f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Send + 'static,
+{
+    let stream = match format {
+        SampleFormat::I8 => output_stream::<i8, _, _>(device, config, callback, error_callback),
+        SampleFormat::I16 => output_stream::<i16, _, _>(device, config, callback, error_callback),
+        SampleFormat::I32 => output_stream::<i32, _, _>(device, config, callback, error_callback),
+        SampleFormat::I64 => output_stream::<i64, _, _>(device, config, callback, error_callback),
+        SampleFormat::U8 => output_stream::<u8, _, _>(device, config, callback, error_callback),
+        SampleFormat::U16 => output_stream::<u16, _, _>(device, config, callback, error_callback),
+        SampleFormat::U32 => output_stream::<u32, _, _>(device, config, callback, error_callback),
+        SampleFormat::U64 => output_stream::<u64, _, _>(device, config, callback, error_callback),
+        SampleFormat::F32 => device.build_output_stream(config, move |data: &mut [f32], _| callback(data), error_callback, None),
+        SampleFormat::F64 => output_stream::<f64, _, _>(device, config, callback, error_callback),
+        other => return Err(format!("지원하지 않는 샘플 포맷: {}", other)),
+    };
+    stream.map_err(|e| e.to_string())
+}
+
+fn output_stream<T, D, E>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut callback: D,
+    error_callback: E,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+    D: FnMut(&mut [f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Send + 'static,
+{
+    let piece_len = MAX_CALLBACK_FRAMES * config.channels as usize;
+    let mut scratch: Vec<f32> = Vec::with_capacity(piece_len);
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            for piece in data.chunks_mut(piece_len) {
+                scratch.resize(piece.len(), 0.0);
+                callback(&mut scratch);
+                for (out, &s) in piece.iter_mut().zip(&scratch) {
+                    *out = T::from_sample(s.clamp(-1.0, 1.0));
+                }
+            }
+        },
+        error_callback,
+        None,
+    )
+}
@@ -0,0 +1,265 @@
+// 노이즈 억제 (ML 없는 스펙트럼 방식: 최소값 추적 잡음 추정 + decision-directed 위너 이득)
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::fft::{self, Fft};
+use crate::peer::{CHANNELS, SAMPLE_RATE};
+
+const FFT_SIZE: usize = 512; // 10.7ms at 48kHz, fine enough to leave harmonics alone
+const HOP: usize = FFT_SIZE / 2;
+const BINS: usize = FFT_SIZE / 2 + 1;
+
+const POWER_SMOOTHING: f32 = 0.8; // Per-bin power average the noise floor tracks
+const DD_ALPHA: f32 = 0.96;       // Decision-directed a priori SNR weight; higher means less musical noise
+const NOISE_EPSILON: f32 = 1e-12;
+
+// Voice: follows a changing noise floor quickly and cuts deep
+const VOICE_NOISE_RISE_DB_PER_SEC: f32 = 6.0;
+const VOICE_MAX_ATTENUATION_DB: f32 = 30.0;
+// Music: slow floor so sustained notes are not learned as noise, shallow cut, tonal peaks kept
+const MUSIC_NOISE_RISE_DB_PER_SEC: f32 = 1.0;
+const MUSIC_MAX_ATTENUATION_DB: f32 = 15.0;
+const MUSIC_GAIN_RELEASE_MS: f32 = 60.0; // Gains fall no faster than this, so note tails decay naturally
+const TONAL_PEAK_RATIO: f32 = 4.0;       // A bin this far above its neighbours two bins away is a partial
+
+/// Delay the suppressor adds while enabled
+pub const LATENCY_MS: f32 = FFT_SIZE as f32 * 1000.0 / SAMPLE_RATE as f32;
+
+/// Processing time against audio time, for one track or stage
+pub struct LoadMeter {
+    busy_ns: AtomicU64,
+    audio_samples: AtomicU64, // Per channel
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoiseSuppressorStats {
+    pub enabled: bool,
+    pub cpu_percent: f32, // Share of one core since the previous read
+    pub latency_ms: f32,
+}
+
+impl LoadMeter {
+    pub fn new() -> Self {
+        Self { busy_ns: AtomicU64::new(0), audio_samples: AtomicU64::new(0) }
+    }
+
+    pub fn record(&self, busy: Duration, samples_per_channel: usize) {
+        self.busy_ns.fetch_add(busy.as_nanos() as u64, Ordering::Relaxed);
+        self.audio_samples.fetch_add(samples_per_channel as u64, Ordering::Relaxed);
+    }
+
+    /// Load since the previous call (which resets it)
+    pub fn take_cpu_percent(&self) -> f32 {
+        let busy_ns = self.busy_ns.swap(0, Ordering::Relaxed);
+        let samples = self.audio_samples.swap(0, Ordering::Relaxed);
+        if samples == 0 {
+            return 0.0;
+        }
+        let audio_ns = samples as f64 * 1e9 / SAMPLE_RATE as f64;
+        (busy_ns as f64 / audio_ns * 100.0) as f32
+    }
+}
+
+impl Default for LoadMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stereo suppressor over a 50%-overlap STFT with sqrt-Hann analysis and synthesis windows.
+/// One gain mask, computed from both channels' power, is applied to each channel so the
+/// stereo image holds. Output lags input by `LATENCY_MS`; nothing allocates after `new`.
+pub struct NoiseSuppressor {
+    fft: Fft,
+    window: Vec<f32>,
+    input: Vec<f32>,  // Last FFT_SIZE samples, per channel
+    overlap: Vec<f32>, // Overlap-add accumulator, per channel
+    output: Vec<f32>, // Finished hop being played out, per channel
+    fill: usize,      // Samples into the current hop
+    re: Vec<f32>,
+    im: Vec<f32>,
+    spectra: Vec<f32>, // Re/im of every channel for the current hop
+    power: Vec<f32>,
+    smoothed: Vec<f32>,
+    noise: Vec<f32>,
+    prev_gain: Vec<f32>,
+    prev_post_snr: Vec<f32>,
+    gain: Vec<f32>,
+    tonal: Vec<bool>, // Bins belonging to a partial this hop (music mode)
+    primed: bool,
+    strength: f32,
+    music_mode: bool,
+}
+
+impl NoiseSuppressor {
+    pub fn new() -> Self {
+        Self {
+            fft: Fft::new(FFT_SIZE),
+            window: fft::hann_window(FFT_SIZE).into_iter().map(f32::sqrt).collect(),
+            input: vec![0.0; FFT_SIZE * CHANNELS],
+            overlap: vec![0.0; FFT_SIZE * CHANNELS],
+            output: vec![0.0; HOP * CHANNELS],
+            fill: 0,
+            re: vec![0.0; FFT_SIZE],
+            im: vec![0.0; FFT_SIZE],
+            spectra: vec![0.0; 2 * FFT_SIZE * CHANNELS],
+            power: vec![0.0; BINS],
+            smoothed: vec![0.0; BINS],
+            noise: vec![0.0; BINS],
+            prev_gain: vec![1.0; BINS],
+            prev_post_snr: vec![1.0; BINS],
+            gain: vec![1.0; BINS],
+            tonal: vec![false; BINS],
+            primed: false,
+            strength: 0.0,
+            music_mode: false,
+        }
+    }
+
+    /// `strength` 0-1: 0 leaves the signal untouched, 1 cuts noise by the mode's maximum
+    pub fn configure(&mut self, strength: f32, music_mode: bool) {
+        self.strength = strength;
+        self.music_mode = music_mode;
+    }
+
+    /// Forget the signal and the learned noise floor
+    pub fn reset(&mut self) {
+        self.input.fill(0.0);
+        self.overlap.fill(0.0);
+        self.output.fill(0.0);
+        self.fill = 0;
+        self.prev_gain.fill(1.0);
+        self.prev_post_snr.fill(1.0);
+        self.primed = false;
+    }
+
+    /// Suppress noise in interleaved stereo, in place
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(CHANNELS) {
+            for (c, s) in frame.iter_mut().enumerate() {
+                self.input[c * FFT_SIZE + FFT_SIZE - HOP + self.fill] = *s;
+                *s = self.output[c * HOP + self.fill];
+            }
+            self.fill += 1;
+            if self.fill == HOP {
+                self.fill = 0;
+                self.run_hop();
+            }
+        }
+    }
+
+    fn run_hop(&mut self) {
+        // Analysis
+        self.power.fill(0.0);
+        for c in 0..CHANNELS {
+            let input = &self.input[c * FFT_SIZE..(c + 1) * FFT_SIZE];
+            for (i, (&x, &w)) in input.iter().zip(&self.window).enumerate() {
+                self.re[i] = x * w;
+                self.im[i] = 0.0;
+            }
+            self.fft.forward(&mut self.re, &mut self.im);
+            for (k, p) in self.power.iter_mut().enumerate() {
+                *p += self.re[k] * self.re[k] + self.im[k] * self.im[k];
+            }
+            let spectrum = &mut self.spectra[c * 2 * FFT_SIZE..(c + 1) * 2 * FFT_SIZE];
+            spectrum[..FFT_SIZE].copy_from_slice(&self.re);
+            spectrum[FFT_SIZE..].copy_from_slice(&self.im);
+        }
+
+        self.update_gains();
+
+        // Synthesis
+        for c in 0..CHANNELS {
+            let spectrum = &self.spectra[c * 2 * FFT_SIZE..(c + 1) * 2 * FFT_SIZE];
+            for k in 0..FFT_SIZE {
+                let g = self.gain[k.min(FFT_SIZE - k)]; // Mirrored bins share the gain
+                self.re[k] = spectrum[k] * g;
+                self.im[k] = spectrum[FFT_SIZE + k] * g;
+            }
+            self.fft.inverse(&mut self.re, &mut self.im);
+            let overlap = &mut self.overlap[c * FFT_SIZE..(c + 1) * FFT_SIZE];
+            for (o, (&x, &w)) in overlap.iter_mut().zip(self.re.iter().zip(&self.window)) {
+                *o += x * w;
+            }
+            // The first hop now has both windows' contributions
+            self.output[c * HOP..(c + 1) * HOP].copy_from_slice(&overlap[..HOP]);
+            overlap.copy_within(HOP.., 0);
+            overlap[FFT_SIZE - HOP..].fill(0.0);
+            self.input.copy_within(c * FFT_SIZE + HOP..(c + 1) * FFT_SIZE, c * FFT_SIZE);
+        }
+    }
+
+    fn update_gains(&mut self) {
+        let (rise_db, max_attenuation_db, over_subtraction) = if self.music_mode {
+            (MUSIC_NOISE_RISE_DB_PER_SEC, MUSIC_MAX_ATTENUATION_DB, 1.0 + 0.5 * self.strength)
+        } else {
+            (VOICE_NOISE_RISE_DB_PER_SEC, VOICE_MAX_ATTENUATION_DB, 1.0 + self.strength)
+        };
+        let hop_secs = HOP as f32 / SAMPLE_RATE as f32;
+        let noise_rise = 10f32.powf(rise_db * hop_secs / 10.0);
+        let floor = 10f32.powf(-self.strength * max_attenuation_db / 20.0);
+        let release = (-hop_secs * 1000.0 / MUSIC_GAIN_RELEASE_MS).exp();
+
+        if !self.primed {
+            self.primed = true;
+            self.smoothed.copy_from_slice(&self.power);
+            self.noise.copy_from_slice(&self.power);
+        }
+        for (s, &p) in self.smoothed.iter_mut().zip(&self.power) {
+            *s = POWER_SMOOTHING * *s + (1.0 - POWER_SMOOTHING) * p;
+        }
+
+        // Peaks are found on the smoothed power: partials persist, noise peaks average out.
+        // A windowed partial spans its peak bin and one bin either side.
+        self.tonal.fill(false);
+        if self.music_mode {
+            for k in 2..BINS - 2 {
+                if self.is_tonal_peak(k) {
+                    self.tonal[k - 1..=k + 1].fill(true);
+                }
+            }
+        }
+
+        for k in 0..BINS {
+            let p = self.power[k];
+            let tonal = self.tonal[k];
+
+            // Minimum tracking: drop to the smoothed power at once, creep up slowly.
+            // Partials never raise the floor, however long they are held.
+            if self.smoothed[k] < self.noise[k] {
+                self.noise[k] = self.smoothed[k];
+            } else if !tonal {
+                self.noise[k] = (self.noise[k] * noise_rise).max(NOISE_EPSILON);
+            }
+
+            let post_snr = p / (over_subtraction * self.noise[k] + NOISE_EPSILON);
+            let prio_snr = DD_ALPHA * self.prev_gain[k] * self.prev_gain[k] * self.prev_post_snr[k]
+                + (1.0 - DD_ALPHA) * (post_snr - 1.0).max(0.0);
+            let mut g = (prio_snr / (1.0 + prio_snr)).max(floor);
+            if self.music_mode {
+                g = g.max(self.prev_gain[k] * release);
+                if tonal {
+                    g = 1.0;
+                }
+            }
+
+            self.prev_gain[k] = g;
+            self.prev_post_snr[k] = post_snr.min(1e6);
+            self.gain[k] = g;
+        }
+    }
+
+    // Narrow spectral peak well above the bins around it (a held note's partial)
+    fn is_tonal_peak(&self, k: usize) -> bool {
+        let s = &self.smoothed;
+        let neighbours = (s[k - 2] + s[k + 2]) * 0.5;
+        s[k] >= s[k - 1] && s[k] >= s[k + 1] && s[k] > TONAL_PEAK_RATIO * neighbours
+    }
+}
+
+impl Default for NoiseSuppressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,1094 @@
+// 입력 처리 체인: 노이즈 억제 → 노이즈 게이트 → 하이패스 → 자동 게인 → 컴프레서 → 파라메트릭 EQ (인코딩 직전, 송신 트랙마다)
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::denoise::{self, LoadMeter, NoiseSuppressor, NoiseSuppressorStats};
+use crate::peer::{CHANNELS, SAMPLE_RATE};
+
+pub const EQ_BANDS: usize = 4;
+
+const GATE_ENVELOPE_RELEASE_MS: f32 = 20.0; // Detector decay; the gate's own release shapes the gain
+const MIN_LEVEL: f32 = 1e-6;                // -120dB, keeps log10 finite
+
+const AGC_DETECTOR_MS: f32 = 300.0;      // RMS window; long enough to ride phrases, not notes
+const AGC_SILENCE_DB: f32 = -55.0;       // Below this the gain holds, so pauses are not boosted
+const AGC_MAX_CUT_DB: f32 = 24.0;
+const AGC_PEAK_CEILING_DB: f32 = -1.0;   // The gain never pushes the recent peak above this
+const AGC_PEAK_RELEASE_MS: f32 = 1000.0;
+const AGC_CALIBRATION_SECS: f32 = 8.0;   // Of signal above the silence threshold
+const AGC_RAMP_MS: f32 = 50.0;           // Glide to a frozen gain instead of stepping
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DspStage {
+    NoiseSuppressor,
+    Gate,
+    HighPass,
+    Agc,
+    Compressor,
+    Eq,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NoiseSuppressorSettings {
+    pub bypass: bool,
+    pub strength: f32,    // 0-1
+    pub music_mode: bool, // Keeps held notes and shallows the cut, for instruments
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GateSettings {
+    pub bypass: bool,
+    pub open_db: f32,  // Opens when the envelope rises above this
+    pub close_db: f32, // Closes when it falls below this; the gap is the hysteresis
+    pub hold_ms: f32,  // Stays open this long after falling below close_db
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    pub floor_db: f32, // Attenuation while closed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HighPassSettings {
+    pub bypass: bool,
+    pub frequency_hz: f32, // 12dB/oct Butterworth
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgcMode {
+    Adaptive,  // Follows the level continuously
+    Calibrate, // Measures a soundcheck, then switches itself to Frozen
+    Frozen,    // Holds frozen_gain_db
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AgcSettings {
+    pub bypass: bool,
+    pub mode: AgcMode,
+    pub target_db: f32,      // RMS level to reach, dBFS
+    pub max_gain_db: f32,    // Boost limit; cuts go down to -24dB
+    pub attack_ms: f32,      // Gain falling
+    pub release_ms: f32,     // Gain rising
+    pub frozen_gain_db: f32, // Applied in Frozen; calibration writes it
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgcStats {
+    pub enabled: bool,
+    pub mode: AgcMode,
+    pub gain_db: f32,
+    pub calibration_progress: f32, // 0-1 while calibrating
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CompressorSettings {
+    pub bypass: bool,
+    pub threshold_db: f32,
+    pub ratio: f32,
+    pub knee_db: f32, // Soft knee width, 0 for a hard knee
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    pub makeup_db: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EqBandKind {
+    LowShelf,
+    Peaking,
+    HighShelf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EqBand {
+    pub kind: EqBandKind,
+    pub frequency_hz: f32,
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EqSettings {
+    pub bypass: bool,
+    pub bands: [EqBand; EQ_BANDS],
+}
+
+/// Every stage's parameters. All stages start bypassed so the default path is untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct DspSettings {
+    pub noise_suppressor: NoiseSuppressorSettings,
+    pub gate: GateSettings,
+    pub high_pass: HighPassSettings,
+    pub agc: AgcSettings,
+    pub compressor: CompressorSettings,
+    pub eq: EqSettings,
+}
+
+impl Default for NoiseSuppressorSettings {
+    fn default() -> Self {
+        Self { bypass: true, strength: 0.7, music_mode: true }
+    }
+}
+
+impl Default for GateSettings {
+    fn default() -> Self {
+        Self {
+            bypass: true,
+            open_db: -46.0, // Same thresholds as the browser's noise gate worklet
+            close_db: -50.0,
+            hold_ms: 50.0,
+            attack_ms: 1.0,
+            release_ms: 100.0,
+            floor_db: -40.0,
+        }
+    }
+}
+
+impl Default for HighPassSettings {
+    fn default() -> Self {
+        Self { bypass: true, frequency_hz: 80.0 }
+    }
+}
+
+impl Default for AgcSettings {
+    fn default() -> Self {
+        Self {
+            bypass: true,
+            mode: AgcMode::Adaptive,
+            target_db: -18.0,
+            max_gain_db: 20.0,
+            attack_ms: 500.0, // Slow both ways so dynamics within a phrase survive
+            release_ms: 4000.0,
+            frozen_gain_db: 0.0,
+        }
+    }
+}
+
+impl Default for CompressorSettings {
+    fn default() -> Self {
+        Self {
+            bypass: true,
+            threshold_db: -12.0, // Same curve as the browser chain's compressor
+            ratio: 4.0,
+            knee_db: 6.0,
+            attack_ms: 3.0,
+            release_ms: 100.0,
+            makeup_db: 0.0,
+        }
+    }
+}
+
+impl Default for EqSettings {
+    fn default() -> Self {
+        let band = |kind, frequency_hz, q| EqBand { kind, frequency_hz, gain_db: 0.0, q };
+        Self {
+            bypass: true,
+            bands: [
+                band(EqBandKind::LowShelf, 320.0, FRAC_1_SQRT_2),
+                band(EqBandKind::Peaking, 1000.0, 1.0),
+                band(EqBandKind::Peaking, 3200.0, 1.0),
+                band(EqBandKind::HighShelf, 8000.0, FRAC_1_SQRT_2),
+            ],
+        }
+    }
+}
+
+impl DspSettings {
+    fn clamped(mut self) -> Self {
+        self.noise_suppressor.strength = self.noise_suppressor.strength.clamp(0.0, 1.0);
+
+        let gate = &mut self.gate;
+        gate.open_db = gate.open_db.clamp(-100.0, 0.0);
+        gate.close_db = gate.close_db.clamp(-100.0, gate.open_db);
+        gate.hold_ms = gate.hold_ms.clamp(0.0, 1000.0);
+        gate.attack_ms = gate.attack_ms.clamp(0.1, 100.0);
+        gate.release_ms = gate.release_ms.clamp(1.0, 2000.0);
+        gate.floor_db = gate.floor_db.clamp(-100.0, 0.0);
+
+        self.high_pass.frequency_hz = self.high_pass.frequency_hz.clamp(20.0, 500.0);
+
+        let agc = &mut self.agc;
+        agc.target_db = agc.target_db.clamp(-40.0, -6.0);
+        agc.max_gain_db = agc.max_gain_db.clamp(0.0, 40.0);
+        agc.attack_ms = agc.attack_ms.clamp(10.0, 5000.0);
+        agc.release_ms = agc.release_ms.clamp(100.0, 20000.0);
+        agc.frozen_gain_db = agc.frozen_gain_db.clamp(-AGC_MAX_CUT_DB, agc.max_gain_db);
+
+        let comp = &mut self.compressor;
+        comp.threshold_db = comp.threshold_db.clamp(-60.0, 0.0);
+        comp.ratio = comp.ratio.clamp(1.0, 20.0);
+        comp.knee_db = comp.knee_db.clamp(0.0, 24.0);
+        comp.attack_ms = comp.attack_ms.clamp(0.1, 200.0);
+        comp.release_ms = comp.release_ms.clamp(5.0, 2000.0);
+        comp.makeup_db = comp.makeup_db.clamp(0.0, 24.0);
+
+        for band in &mut self.eq.bands {
+            band.frequency_hz = band.frequency_hz.clamp(20.0, 20000.0);
+            band.gain_db = band.gain_db.clamp(-24.0, 24.0);
+            band.q = band.q.clamp(0.1, 10.0);
+        }
+        self
+    }
+
+    fn set_bypass(&mut self, stage: DspStage, bypass: bool) {
+        match stage {
+            DspStage::NoiseSuppressor => self.noise_suppressor.bypass = bypass,
+            DspStage::Gate => self.gate.bypass = bypass,
+            DspStage::HighPass => self.high_pass.bypass = bypass,
+            DspStage::Agc => self.agc.bypass = bypass,
+            DspStage::Compressor => self.compressor.bypass = bypass,
+            DspStage::Eq => self.eq.bypass = bypass,
+        }
+    }
+}
+
+/// Every send track's settings, shared by the commands and the tracks' chains. Chains
+/// compare the generation once per frame and reload their own settings when it moved.
+pub struct DspControl {
+    settings: Mutex<BTreeMap<u8, DspSettings>>, // By stream ID; tracks without an entry run the defaults
+    generation: AtomicU32,
+    meters: Mutex<BTreeMap<u8, Arc<TrackMeters>>>, // By stream ID
+}
+
+// One track's readings, written by its chain
+#[derive(Default)]
+struct TrackMeters {
+    denoise_load: LoadMeter,
+    agc_gain_db: AtomicU32,     // f32 bits
+    agc_calibration: AtomicU32, // f32 bits, 0-1
+}
+
+impl DspControl {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(BTreeMap::new()),
+            generation: AtomicU32::new(0),
+            meters: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn update(&self, stream_id: u8, change: impl FnOnce(&mut DspSettings)) {
+        if let Ok(mut all) = self.settings.lock() {
+            change(all.entry(stream_id).or_default());
+        }
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn set_settings(&self, stream_id: u8, settings: DspSettings) {
+        self.update(stream_id, |current| *current = settings.clamped());
+    }
+
+    pub fn set_bypass(&self, stream_id: u8, stage: DspStage, bypass: bool) {
+        self.update(stream_id, |current| current.set_bypass(stage, bypass));
+    }
+
+    /// Switch the track's AGC on and into calibration; it freezes itself when the soundcheck is measured
+    pub fn start_agc_calibration(&self, stream_id: u8) {
+        self.update(stream_id, |current| {
+            current.agc.bypass = false;
+            current.agc.mode = AgcMode::Calibrate;
+        });
+        self.meters(stream_id).agc_calibration.store(0f32.to_bits(), Ordering::Relaxed);
+    }
+
+    fn finish_agc_calibration(&self, stream_id: u8, gain_db: f32) {
+        if let Ok(mut all) = self.settings.lock() {
+            let Some(current) = all.get_mut(&stream_id) else { return };
+            if current.agc.mode != AgcMode::Calibrate {
+                return;
+            }
+            current.agc.mode = AgcMode::Frozen;
+            current.agc.frozen_gain_db = gain_db.clamp(-AGC_MAX_CUT_DB, current.agc.max_gain_db);
+        }
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// Forget a removed track, so a new track reusing its stream ID starts from the defaults
+    pub fn remove_track(&self, stream_id: u8) {
+        if let Ok(mut all) = self.settings.lock() {
+            all.remove(&stream_id);
+        }
+        if let Ok(mut meters) = self.meters.lock() {
+            meters.remove(&stream_id);
+        }
+    }
+
+    fn meters(&self, stream_id: u8) -> Arc<TrackMeters> {
+        self.meters
+            .lock()
+            .map(|mut meters| meters.entry(stream_id).or_default().clone())
+            .unwrap_or_default()
+    }
+
+    pub fn settings(&self, stream_id: u8) -> DspSettings {
+        self.settings
+            .lock()
+            .ok()
+            .and_then(|all| all.get(&stream_id).copied())
+            .unwrap_or_default()
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// The track's suppressor CPU load since the previous call, and the delay it adds
+    pub fn noise_suppressor_stats(&self, stream_id: u8) -> NoiseSuppressorStats {
+        NoiseSuppressorStats {
+            enabled: !self.settings(stream_id).noise_suppressor.bypass,
+            cpu_percent: self.meters(stream_id).denoise_load.take_cpu_percent(),
+            latency_ms: denoise::LATENCY_MS,
+        }
+    }
+
+    pub fn agc_stats(&self, stream_id: u8) -> AgcStats {
+        let agc = self.settings(stream_id).agc;
+        let meters = self.meters(stream_id);
+        AgcStats {
+            enabled: !agc.bypass,
+            mode: agc.mode,
+            gain_db: f32::from_bits(meters.agc_gain_db.load(Ordering::Relaxed)),
+            calibration_progress: f32::from_bits(meters.agc_calibration.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Default for DspControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One send track's processing state. Runs on the encoder thread, in place on interleaved
+/// stereo frames; nothing allocates after construction.
+pub struct InputChain {
+    control: Arc<DspControl>,
+    stream_id: u8,
+    meters: Arc<TrackMeters>,
+    generation: u32,
+    settings: DspSettings,
+    noise_suppressor: NoiseSuppressor,
+    gate: Gate,
+    high_pass: Biquad,
+    agc: Agc,
+    compressor: Compressor,
+    eq: [Biquad; EQ_BANDS],
+}
+
+impl InputChain {
+    pub fn new(control: Arc<DspControl>, stream_id: u8) -> Self {
+        let settings = control.settings(stream_id);
+        let mut chain = Self {
+            generation: control.generation(),
+            meters: control.meters(stream_id),
+            control,
+            stream_id,
+            settings,
+            noise_suppressor: NoiseSuppressor::new(),
+            gate: Gate::default(),
+            high_pass: Biquad::default(),
+            agc: Agc::default(),
+            compressor: Compressor::default(),
+            eq: [Biquad::default(); EQ_BANDS],
+        };
+        chain.configure(settings);
+        chain
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let generation = self.control.generation();
+        if generation != self.generation {
+            self.generation = generation;
+            self.configure(self.control.settings(self.stream_id));
+        }
+
+        if !self.settings.noise_suppressor.bypass {
+            let start = Instant::now();
+            self.noise_suppressor.process(samples);
+            self.meters.denoise_load.record(start.elapsed(), samples.len() / CHANNELS);
+        }
+        if !self.settings.gate.bypass {
+            self.gate.process(samples);
+        }
+        if !self.settings.high_pass.bypass {
+            self.high_pass.process(samples);
+        }
+        if !self.settings.agc.bypass {
+            if let Some(gain_db) = self.agc.process(samples) {
+                self.control.finish_agc_calibration(self.stream_id, gain_db);
+            }
+            self.meters.agc_gain_db.store(self.agc.gain_db.to_bits(), Ordering::Relaxed);
+            self.meters.agc_calibration.store(self.agc.calibration_progress().to_bits(), Ordering::Relaxed);
+        }
+        if !self.settings.compressor.bypass {
+            self.compressor.process(samples);
+        }
+        if !self.settings.eq.bypass {
+            for band in &mut self.eq {
+                band.process(samples);
+            }
+        }
+    }
+
+    fn configure(&mut self, settings: DspSettings) {
+        // A stage coming out of bypass starts from silence, not from stale state
+        if settings.noise_suppressor.bypass != self.settings.noise_suppressor.bypass {
+            self.noise_suppressor.reset();
+        }
+        if settings.gate.bypass != self.settings.gate.bypass {
+            self.gate.reset();
+        }
+        if settings.high_pass.bypass != self.settings.high_pass.bypass {
+            self.high_pass.reset();
+        }
+        if settings.agc.bypass != self.settings.agc.bypass {
+            self.agc.reset();
+        } else if settings.agc.mode == AgcMode::Calibrate && self.settings.agc.mode != AgcMode::Calibrate {
+            self.agc.restart_calibration();
+        }
+        if settings.compressor.bypass != self.settings.compressor.bypass {
+            self.compressor.reset();
+        }
+        if settings.eq.bypass != self.settings.eq.bypass {
+            self.eq.iter_mut().for_each(Biquad::reset);
+        }
+
+        self.noise_suppressor.configure(settings.noise_suppressor.strength, settings.noise_suppressor.music_mode);
+        self.gate.configure(&settings.gate);
+        self.high_pass.set(high_pass_coeffs(settings.high_pass.frequency_hz));
+        self.agc.configure(&settings.agc);
+        self.compressor.configure(&settings.compressor);
+        for (filter, band) in self.eq.iter_mut().zip(&settings.eq.bands) {
+            filter.set(eq_coeffs(band));
+        }
+        self.settings = settings;
+    }
+}
+
+// One-pole smoothing coefficient reaching ~63% in `ms`
+fn time_coeff(ms: f32) -> f32 {
+    1.0 - (-1000.0 / (ms * SAMPLE_RATE as f32)).exp()
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+// Stereo-linked gate: peak envelope, open/close thresholds with hold, smoothed gain
+struct Gate {
+    open_level: f32,
+    close_level: f32,
+    floor: f32,
+    hold_samples: u32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    envelope_coeff: f32,
+    envelope: f32,
+    open: bool,
+    hold_left: u32,
+    gain: f32,
+}
+
+impl Default for Gate {
+    fn default() -> Self {
+        let mut gate = Self {
+            open_level: 0.0,
+            close_level: 0.0,
+            floor: 0.0,
+            hold_samples: 0,
+            attack_coeff: 1.0,
+            release_coeff: 1.0,
+            envelope_coeff: time_coeff(GATE_ENVELOPE_RELEASE_MS),
+            envelope: 0.0,
+            open: false,
+            hold_left: 0,
+            gain: 0.0,
+        };
+        gate.configure(&GateSettings::default());
+        gate.reset();
+        gate
+    }
+}
+
+impl Gate {
+    fn configure(&mut self, s: &GateSettings) {
+        self.open_level = db_to_gain(s.open_db);
+        self.close_level = db_to_gain(s.close_db);
+        self.floor = db_to_gain(s.floor_db);
+        self.hold_samples = (s.hold_ms * SAMPLE_RATE as f32 / 1000.0) as u32;
+        self.attack_coeff = time_coeff(s.attack_ms);
+        self.release_coeff = time_coeff(s.release_ms);
+    }
+
+    fn reset(&mut self) {
+        self.envelope = 0.0;
+        self.open = false;
+        self.hold_left = 0;
+        self.gain = self.floor;
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(CHANNELS) {
+            let peak = frame.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            self.envelope = if peak > self.envelope {
+                peak
+            } else {
+                self.envelope + (peak - self.envelope) * self.envelope_coeff
+            };
+
+            if self.envelope > self.open_level {
+                self.open = true;
+                self.hold_left = self.hold_samples;
+            } else if self.envelope < self.close_level {
+                if self.hold_left > 0 {
+                    self.hold_left -= 1;
+                } else {
+                    self.open = false;
+                }
+            }
+
+            let (target, coeff) = if self.open {
+                (1.0, self.attack_coeff)
+            } else {
+                (self.floor, self.release_coeff)
+            };
+            self.gain += (target - self.gain) * coeff;
+            for s in frame.iter_mut() {
+                *s *= self.gain;
+            }
+        }
+    }
+}
+
+// Stereo-linked slow AGC on an RMS detector. Holds through silence, drops at once when the
+// recent peak would cross the ceiling, and in calibration holds its gain while it measures
+// the soundcheck.
+struct Agc {
+    mode: AgcMode,
+    target_db: f32,
+    max_gain_db: f32,
+    frozen_gain_db: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    detector_coeff: f32,
+    peak_coeff: f32,
+    ramp_coeff: f32,
+    mean_square: f32,
+    peak: f32,
+    gain_db: f32,
+    calibration_energy: f64, // Sum of active frames' power
+    calibration_frames: u32,
+    calibration_peak: f32,
+}
+
+impl Default for Agc {
+    fn default() -> Self {
+        let mut agc = Self {
+            mode: AgcMode::Adaptive,
+            target_db: 0.0,
+            max_gain_db: 0.0,
+            frozen_gain_db: 0.0,
+            attack_coeff: 1.0,
+            release_coeff: 1.0,
+            detector_coeff: time_coeff(AGC_DETECTOR_MS),
+            peak_coeff: time_coeff(AGC_PEAK_RELEASE_MS),
+            ramp_coeff: time_coeff(AGC_RAMP_MS),
+            mean_square: 0.0,
+            peak: 0.0,
+            gain_db: 0.0,
+            calibration_energy: 0.0,
+            calibration_frames: 0,
+            calibration_peak: 0.0,
+        };
+        agc.configure(&AgcSettings::default());
+        agc
+    }
+}
+
+impl Agc {
+    fn configure(&mut self, s: &AgcSettings) {
+        self.mode = s.mode;
+        self.target_db = s.target_db;
+        self.max_gain_db = s.max_gain_db;
+        self.frozen_gain_db = s.frozen_gain_db;
+        self.attack_coeff = time_coeff(s.attack_ms);
+        self.release_coeff = time_coeff(s.release_ms);
+    }
+
+    fn reset(&mut self) {
+        self.mean_square = 0.0;
+        self.peak = 0.0;
+        self.gain_db = 0.0;
+        self.restart_calibration();
+    }
+
+    fn restart_calibration(&mut self) {
+        self.calibration_energy = 0.0;
+        self.calibration_frames = 0;
+        self.calibration_peak = 0.0;
+    }
+
+    fn calibration_frames_needed() -> u32 {
+        (AGC_CALIBRATION_SECS * SAMPLE_RATE as f32) as u32
+    }
+
+    fn calibration_progress(&self) -> f32 {
+        if self.mode != AgcMode::Calibrate {
+            return 0.0;
+        }
+        self.calibration_frames as f32 / Self::calibration_frames_needed() as f32
+    }
+
+    // Gain that brings a level to the target without pushing its peak over the ceiling
+    fn gain_for(&self, level_db: f32, peak: f32) -> f32 {
+        (self.target_db - level_db).clamp(-AGC_MAX_CUT_DB, self.max_gain_db).min(peak_limit(peak))
+    }
+
+    /// Returns the measured gain once a calibration completes
+    fn process(&mut self, samples: &mut [f32]) -> Option<f32> {
+        let mut calibrated = None;
+        for frame in samples.chunks_exact_mut(CHANNELS) {
+            let (sum_sq, peak) = frame.iter().fold((0.0f32, 0.0f32), |(e, m), s| (e + s * s, m.max(s.abs())));
+            let power = sum_sq / CHANNELS as f32;
+            self.mean_square += (power - self.mean_square) * self.detector_coeff;
+            self.peak = if peak > self.peak { peak } else { self.peak + (peak - self.peak) * self.peak_coeff };
+            let level_db = 10.0 * self.mean_square.max(MIN_LEVEL * MIN_LEVEL).log10();
+            let active = level_db > AGC_SILENCE_DB;
+
+            match self.mode {
+                AgcMode::Adaptive => {
+                    if active {
+                        let target = self.gain_for(level_db, self.peak);
+                        let coeff = if target < self.gain_db { self.attack_coeff } else { self.release_coeff };
+                        self.gain_db += (target - self.gain_db) * coeff;
+                    }
+                    // A sudden loud entry is not left to the slow attack
+                    self.gain_db = self.gain_db.min(peak_limit(self.peak));
+                }
+                AgcMode::Calibrate => {
+                    if active && calibrated.is_none() {
+                        self.calibration_energy += power as f64;
+                        self.calibration_frames += 1;
+                        self.calibration_peak = self.calibration_peak.max(peak);
+                        if self.calibration_frames >= Self::calibration_frames_needed() {
+                            let mean = self.calibration_energy / self.calibration_frames as f64;
+                            let level_db = 10.0 * (mean as f32).max(MIN_LEVEL * MIN_LEVEL).log10();
+                            calibrated = Some(self.gain_for(level_db, self.calibration_peak));
+                        }
+                    }
+                }
+                AgcMode::Frozen => self.gain_db += (self.frozen_gain_db - self.gain_db) * self.ramp_coeff,
+            }
+
+            let gain = db_to_gain(self.gain_db);
+            for s in frame.iter_mut() {
+                *s *= gain;
+            }
+        }
+        calibrated
+    }
+}
+
+fn peak_limit(peak: f32) -> f32 {
+    AGC_PEAK_CEILING_DB - 20.0 * peak.max(MIN_LEVEL).log10()
+}
+
+// Stereo-linked feed-forward compressor with a soft knee, smoothed in the dB domain
+struct Compressor {
+    threshold_db: f32,
+    slope: f32, // 1/ratio - 1
+    knee_db: f32,
+    makeup_db: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    reduction_db: f32, // Smoothed, <= 0
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        let mut comp = Self {
+            threshold_db: 0.0,
+            slope: 0.0,
+            knee_db: 0.0,
+            makeup_db: 0.0,
+            attack_coeff: 1.0,
+            release_coeff: 1.0,
+            reduction_db: 0.0,
+        };
+        comp.configure(&CompressorSettings::default());
+        comp
+    }
+}
+
+impl Compressor {
+    fn configure(&mut self, s: &CompressorSettings) {
+        self.threshold_db = s.threshold_db;
+        self.slope = 1.0 / s.ratio - 1.0;
+        self.knee_db = s.knee_db;
+        self.makeup_db = s.makeup_db;
+        self.attack_coeff = time_coeff(s.attack_ms);
+        self.release_coeff = time_coeff(s.release_ms);
+    }
+
+    fn reset(&mut self) {
+        self.reduction_db = 0.0;
+    }
+
+    // Static curve: gain change in dB for a detector level
+    fn curve(&self, level_db: f32) -> f32 {
+        let over = level_db - self.threshold_db;
+        if 2.0 * over <= -self.knee_db {
+            0.0
+        } else if 2.0 * over < self.knee_db {
+            let x = over + self.knee_db / 2.0;
+            self.slope * x * x / (2.0 * self.knee_db)
+        } else {
+            self.slope * over
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(CHANNELS) {
+            let peak = frame.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            let target = self.curve(20.0 * peak.max(MIN_LEVEL).log10());
+            let coeff = if target < self.reduction_db { self.attack_coeff } else { self.release_coeff };
+            self.reduction_db += (target - self.reduction_db) * coeff;
+            let gain = db_to_gain(self.reduction_db + self.makeup_db);
+            for s in frame.iter_mut() {
+                *s *= gain;
+            }
+        }
+    }
+}
+
+// Second-order section, transposed direct form II, one state pair per channel
+#[derive(Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    state: [[f32; 2]; CHANNELS],
+}
+
+impl Default for Biquad {
+    fn default() -> Self {
+        Self { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0, state: [[0.0; 2]; CHANNELS] }
+    }
+}
+
+impl Biquad {
+    // Coefficients are [b0, b1, b2, a0, a1, a2]; a coefficient change keeps the state
+    fn set(&mut self, c: [f32; 6]) {
+        self.b0 = c[0] / c[3];
+        self.b1 = c[1] / c[3];
+        self.b2 = c[2] / c[3];
+        self.a1 = c[4] / c[3];
+        self.a2 = c[5] / c[3];
+    }
+
+    fn reset(&mut self) {
+        self.state = [[0.0; 2]; CHANNELS];
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(CHANNELS) {
+            for (x, z) in frame.iter_mut().zip(self.state.iter_mut()) {
+                let input = *x;
+                let y = self.b0 * input + z[0];
+                z[0] = self.b1 * input - self.a1 * y + z[1];
+                z[1] = self.b2 * input - self.a2 * y;
+                *x = y;
+            }
+        }
+    }
+}
+
+// RBJ audio EQ cookbook
+fn omega(frequency_hz: f32) -> (f32, f32) {
+    let w0 = 2.0 * PI * frequency_hz.min(SAMPLE_RATE as f32 * 0.45) / SAMPLE_RATE as f32;
+    (w0.cos(), w0.sin())
+}
+
+fn high_pass_coeffs(frequency_hz: f32) -> [f32; 6] {
+    let (cos, sin) = omega(frequency_hz);
+    let alpha = sin / (2.0 * FRAC_1_SQRT_2);
+    [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha]
+}
+
+fn eq_coeffs(band: &EqBand) -> [f32; 6] {
+    let (cos, sin) = omega(band.frequency_hz);
+    let alpha = sin / (2.0 * band.q);
+    let a = 10f32.powf(band.gain_db / 40.0);
+    let k = 2.0 * a.sqrt() * alpha;
+    match band.kind {
+        EqBandKind::Peaking => [
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        ],
+        EqBandKind::LowShelf => [
+            a * ((a + 1.0) - (a - 1.0) * cos + k),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - k),
+            (a + 1.0) + (a - 1.0) * cos + k,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - k,
+        ],
+        EqBandKind::HighShelf => [
+            a * ((a + 1.0) + (a - 1.0) * cos + k),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - k),
+            (a + 1.0) - (a - 1.0) * cos + k,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - k,
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(ms: u32) -> u32 {
+        ms * SAMPLE_RATE / 1000
+    }
+
+    // One stereo frame at a constant level, so the gate's peak envelope sees exactly it
+    fn gate_step(gate: &mut Gate, level: f32) {
+        gate.process(&mut [level; CHANNELS]);
+    }
+
+    #[test]
+    fn gate_opens_above_open_db_holds_then_falls_to_floor() {
+        let settings = GateSettings { bypass: false, ..GateSettings::default() };
+        let mut gate = Gate::default();
+        gate.configure(&settings);
+        gate.reset();
+        let floor = db_to_gain(settings.floor_db);
+
+        // Between the thresholds a closed gate stays closed
+        let between = db_to_gain((settings.open_db + settings.close_db) / 2.0);
+        for _ in 0..frames(200) {
+            gate_step(&mut gate, between);
+        }
+        assert!(!gate.open);
+        assert!((gate.gain - floor).abs() < 1e-6, "gain {}", gate.gain);
+
+        // Above open_db it opens within a few attack times
+        let loud = db_to_gain(settings.open_db + 10.0);
+        for _ in 0..frames(20) {
+            gate_step(&mut gate, loud);
+        }
+        assert!(gate.open);
+        assert!(gate.gain > 0.99, "gain {}", gate.gain);
+
+        // Once the envelope is below close_db it stays fully open for hold_ms
+        let mut held = 0;
+        for _ in 0..frames(2000) {
+            gate_step(&mut gate, 0.0);
+            if gate.open {
+                assert!(gate.gain > 0.999, "gain {} while open", gate.gain);
+                if gate.envelope < gate.close_level {
+                    held += 1;
+                }
+            }
+        }
+        assert_eq!(held, frames(settings.hold_ms as u32));
+
+        // ...then releases down to floor_db
+        assert!(!gate.open);
+        assert!((gate.gain / floor - 1.0).abs() < 0.01, "gain {} floor {}", gate.gain, floor);
+    }
+
+    #[test]
+    fn compressor_curve_is_continuous_with_the_ratio_above_the_knee() {
+        const EPS: f32 = 1e-3;
+        for (ratio, knee_db) in [(4.0, 6.0), (2.0, 12.0), (10.0, 0.0)] {
+            let settings = CompressorSettings { ratio, knee_db, ..CompressorSettings::default() };
+            let mut comp = Compressor::default();
+            comp.configure(&settings);
+            let threshold = settings.threshold_db;
+            let derivative = |level: f32| (comp.curve(level + EPS) - comp.curve(level - EPS)) / (2.0 * EPS);
+
+            assert_eq!(comp.curve(threshold - knee_db / 2.0 - 10.0), 0.0);
+            for edge in [threshold - knee_db / 2.0, threshold + knee_db / 2.0] {
+                let step = comp.curve(edge + EPS) - comp.curve(edge - EPS);
+                assert!(step.abs() < 1e-2, "ratio {} knee {}: jump {} at {}", ratio, knee_db, step, edge);
+                if knee_db > 0.0 {
+                    let kink = derivative(edge + 2.0 * EPS) - derivative(edge - 2.0 * EPS);
+                    assert!(kink.abs() < 1e-2, "ratio {} knee {}: kink {} at {}", ratio, knee_db, kink, edge);
+                }
+            }
+
+            // Output level rises 1/ratio dB per input dB above the knee
+            for level in [threshold + knee_db / 2.0 + 1.0, threshold + 20.0] {
+                let out = |l: f32| l + comp.curve(l);
+                let slope = out(level + 1.0) - out(level);
+                assert!((slope - 1.0 / ratio).abs() < 1e-3, "ratio {} knee {}: slope {}", ratio, knee_db, slope);
+            }
+        }
+    }
+
+    // Steady-state gain of a filter for a cosine, correlated over one settled second,
+    // which is a whole number of cycles at every frequency tested
+    fn response_db(coeffs: [f32; 6], frequency_hz: f32) -> f32 {
+        let mut filter = Biquad::default();
+        filter.set(coeffs);
+        let n = SAMPLE_RATE as usize;
+        let w = 2.0 * std::f64::consts::PI * frequency_hz as f64 / SAMPLE_RATE as f64;
+        let mut samples: Vec<f32> = (0..2 * n).flat_map(|i| [(w * i as f64).cos() as f32; CHANNELS]).collect();
+        filter.process(&mut samples);
+
+        let (mut re, mut im) = (0.0f64, 0.0f64);
+        for (i, frame) in samples.chunks_exact(CHANNELS).enumerate().skip(n) {
+            re += frame[0] as f64 * (w * i as f64).cos();
+            im += frame[0] as f64 * (w * i as f64).sin();
+        }
+        // At DC and Nyquist the tone has no quadrature part to share its energy with
+        let edge = frequency_hz == 0.0 || 2.0 * frequency_hz == SAMPLE_RATE as f32;
+        let amplitude = re.hypot(im) / n as f64 * if edge { 1.0 } else { 2.0 };
+        20.0 * amplitude.max(1e-12).log10() as f32
+    }
+
+    fn assert_db(actual: f32, expected: f32, what: &str) {
+        assert!((actual - expected).abs() < 0.05, "{}: {:.3}dB, expected {:.3}dB", what, actual, expected);
+    }
+
+    #[test]
+    fn high_pass_cuts_dc_and_is_3db_down_at_the_corner() {
+        let nyquist = SAMPLE_RATE as f32 / 2.0;
+        for frequency_hz in [40.0, 80.0, 200.0] {
+            let coeffs = high_pass_coeffs(frequency_hz);
+            assert!(response_db(coeffs, 0.0) < -60.0);
+            assert_db(response_db(coeffs, frequency_hz), -3.01, "corner");
+            assert_db(response_db(coeffs, nyquist), 0.0, "nyquist");
+        }
+    }
+
+    #[test]
+    fn eq_bands_have_their_gain_where_expected() {
+        let nyquist = SAMPLE_RATE as f32 / 2.0;
+        for gain_db in [6.0, -9.0] {
+            let band = |kind, frequency_hz, q| EqBand { kind, frequency_hz, gain_db, q };
+
+            // Shelves reach half their gain at the corner
+            let low = eq_coeffs(&band(EqBandKind::LowShelf, 320.0, FRAC_1_SQRT_2));
+            assert_db(response_db(low, 0.0), gain_db, "low shelf dc");
+            assert_db(response_db(low, 320.0), gain_db / 2.0, "low shelf corner");
+            assert_db(response_db(low, nyquist), 0.0, "low shelf nyquist");
+
+            let high = eq_coeffs(&band(EqBandKind::HighShelf, 8000.0, FRAC_1_SQRT_2));
+            assert_db(response_db(high, 0.0), 0.0, "high shelf dc");
+            assert_db(response_db(high, 8000.0), gain_db / 2.0, "high shelf corner");
+            assert_db(response_db(high, nyquist), gain_db, "high shelf nyquist");
+
+            let peak = eq_coeffs(&band(EqBandKind::Peaking, 1000.0, 1.0));
+            assert_db(response_db(peak, 0.0), 0.0, "peaking dc");
+            assert_db(response_db(peak, 1000.0), gain_db, "peaking centre");
+            assert_db(response_db(peak, nyquist), 0.0, "peaking nyquist");
+        }
+    }
+
+    // 5ms of a 1kHz sine at an RMS level; a whole number of cycles, so blocks repeat seamlessly
+    fn tone_block(rms_db: f32) -> Vec<f32> {
+        let amplitude = db_to_gain(rms_db) * std::f32::consts::SQRT_2;
+        (0..frames(5))
+            .flat_map(|i| [amplitude * (2.0 * PI * 1000.0 * i as f32 / SAMPLE_RATE as f32).sin(); CHANNELS])
+            .collect()
+    }
+
+    fn feed(chain: &mut InputChain, block: &[f32], ms: u32) {
+        for _ in 0..ms / 5 {
+            chain.process(&mut block.to_vec());
+        }
+    }
+
+    #[test]
+    fn agc_calibration_measures_the_soundcheck_then_freezes() {
+        let control = Arc::new(DspControl::new());
+        control.start_agc_calibration(0);
+        let mut chain = InputChain::new(control.clone(), 0);
+        let target_db = AgcSettings::default().target_db;
+
+        // Silence does not count towards the soundcheck
+        feed(&mut chain, &tone_block(AGC_SILENCE_DB - 20.0), 2000);
+        assert_eq!(control.agc_stats(0).calibration_progress, 0.0);
+
+        let level_db = -30.0;
+        let tone = tone_block(level_db);
+        feed(&mut chain, &tone, 4000);
+        let progress = control.agc_stats(0).calibration_progress;
+        assert!((progress - 0.5).abs() < 0.005, "progress {}", progress);
+        feed(&mut chain, &tone, 3900);
+        assert_eq!(control.settings(0).agc.mode, AgcMode::Calibrate);
+        assert_eq!(control.agc_stats(0).gain_db, 0.0); // Held while measuring
+
+        for _ in 0..40 {
+            if control.settings(0).agc.mode == AgcMode::Frozen {
+                break;
+            }
+            feed(&mut chain, &tone, 5);
+        }
+        let agc = control.settings(0).agc;
+        assert_eq!(agc.mode, AgcMode::Frozen);
+        assert!((agc.frozen_gain_db - (target_db - level_db)).abs() < 0.1, "frozen at {}dB", agc.frozen_gain_db);
+
+        // The frozen gain is reached over AGC_RAMP_MS, not in one step
+        feed(&mut chain, &tone, AGC_RAMP_MS as u32);
+        let ramped = control.agc_stats(0).gain_db / agc.frozen_gain_db;
+        assert!((ramped - 0.63).abs() < 0.05, "{} of the gain after one ramp time", ramped);
+        feed(&mut chain, &tone, 10 * AGC_RAMP_MS as u32);
+        let gain_db = control.agc_stats(0).gain_db;
+        assert!((gain_db - agc.frozen_gain_db).abs() < 0.01, "gain {}dB", gain_db);
+    }
+
+    #[test]
+    fn agc_calibration_is_clamped_to_max_gain() {
+        let control = Arc::new(DspControl::new());
+        control.start_agc_calibration(1);
+        let mut chain = InputChain::new(control.clone(), 1);
+        feed(&mut chain, &tone_block(AGC_SILENCE_DB + 5.0), 8200);
+
+        let agc = control.settings(1).agc;
+        assert_eq!(agc.mode, AgcMode::Frozen);
+        assert_eq!(agc.frozen_gain_db, agc.max_gain_db);
+    }
+
+    #[test]
+    fn settings_are_clamped_into_range() {
+        let mut settings = DspSettings::default();
+        settings.gate.open_db = -30.0;
+        settings.gate.close_db = -20.0;
+        settings.gate.hold_ms = 5000.0;
+        settings.gate.attack_ms = 0.0;
+        settings.high_pass.frequency_hz = 5.0;
+        settings.compressor.ratio = 0.5;
+        settings.compressor.knee_db = -3.0;
+        settings.eq.bands[0].gain_db = 40.0;
+        settings.eq.bands[1].q = 0.0;
+        settings.eq.bands[3].frequency_hz = 30000.0;
+
+        let clamped = settings.clamped();
+        assert_eq!(clamped.gate.close_db, -30.0); // Never above open_db
+        assert_eq!(clamped.gate.hold_ms, 1000.0);
+        assert_eq!(clamped.gate.attack_ms, 0.1);
+        assert_eq!(clamped.high_pass.frequency_hz, 20.0);
+        assert_eq!(clamped.compressor.ratio, 1.0);
+        assert_eq!(clamped.compressor.knee_db, 0.0);
+        assert_eq!(clamped.eq.bands[0].gain_db, 24.0);
+        assert_eq!(clamped.eq.bands[1].q, 0.1);
+        assert_eq!(clamped.eq.bands[3].frequency_hz, 20000.0);
+
+        // In-range settings pass through untouched
+        assert_eq!(DspSettings::default().clamped(), DspSettings::default());
+    }
+}
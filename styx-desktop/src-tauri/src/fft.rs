@@ -0,0 +1,83 @@
+// 고속 푸리에 변환 (radix-2, 복소수 in-place)
+#![allow(dead_code)]
+
+// Twiddles and bit-reversal table are precomputed so transforms on the audio path
+// neither allocate nor call trig functions.
+pub struct Fft {
+    n: usize,
+    cos: Vec<f32>,
+    sin: Vec<f32>,
+    bitrev: Vec<usize>,
+}
+
+impl Fft {
+    pub fn new(n: usize) -> Self {
+        assert!(n.is_power_of_two() && n >= 2, "FFT size must be a power of two");
+        let half = n / 2;
+        let cos = (0..half).map(|k| (2.0 * std::f64::consts::PI * k as f64 / n as f64).cos() as f32).collect();
+        let sin = (0..half).map(|k| (2.0 * std::f64::consts::PI * k as f64 / n as f64).sin() as f32).collect();
+        let bits = n.trailing_zeros();
+        let bitrev = (0..n).map(|i| i.reverse_bits() >> (usize::BITS - bits)).collect();
+        Self { n, cos, sin, bitrev }
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn forward(&self, re: &mut [f32], im: &mut [f32]) {
+        self.transform(re, im, false);
+    }
+
+    /// Inverse transform, scaled by 1/n
+    pub fn inverse(&self, re: &mut [f32], im: &mut [f32]) {
+        self.transform(re, im, true);
+        let scale = 1.0 / self.n as f32;
+        for (r, i) in re.iter_mut().zip(im.iter_mut()) {
+            *r *= scale;
+            *i *= scale;
+        }
+    }
+
+    fn transform(&self, re: &mut [f32], im: &mut [f32], inverse: bool) {
+        let n = self.n;
+        assert!(re.len() == n && im.len() == n);
+
+        for i in 0..n {
+            let j = self.bitrev[i];
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+
+        let sign = if inverse { 1.0 } else { -1.0 };
+        let mut len = 2;
+        while len <= n {
+            let half = len / 2;
+            let step = n / len;
+            for start in (0..n).step_by(len) {
+                for k in 0..half {
+                    let wr = self.cos[k * step];
+                    let wi = sign * self.sin[k * step];
+                    let a = start + k;
+                    let b = a + half;
+                    let tr = re[b] * wr - im[b] * wi;
+                    let ti = re[b] * wi + im[b] * wr;
+                    re[b] = re[a] - tr;
+                    im[b] = im[a] - ti;
+                    re[a] += tr;
+                    im[a] += ti;
+                }
+            }
+            len <<= 1;
+        }
+    }
+}
+
+/// Periodic Hann window (sums to a constant at 50% overlap)
+pub fn hann_window(n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / n as f32).cos())
+        .collect()
+}
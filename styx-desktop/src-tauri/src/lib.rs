@@ -2,6 +2,21 @@ mod audio;
 mod udp;
 mod stream;
 mod peer;
+mod stats;
+mod sim;
+mod fft;
+mod quality;
+mod resample;
+mod mixer;
+mod limiter;
+mod lossless;
+mod codec;
+mod dsp;
+mod denoise;
+mod convert;
+mod monitor;
+mod priority;
+mod aec;
 
 use std::sync::Mutex;
 use std::sync::atomic::Ordering;
@@ -106,12 +121,156 @@ fn udp_add_peer(addr: String, state: State<'_, AppState>) -> Result<(), String>
 
 #[tauri::command]
 fn udp_set_muted(muted: bool, state: State<'_, AppState>) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    for track in stream_state.send_tracks() {
+        track.is_muted.store(muted, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+// ===== 추가 송신 트랙 (다음 스트림 시작 시 적용, stream_id 0은 기본 입력) =====
+
+#[tauri::command]
+fn add_send_track(
+    input_device: Option<String>,
+    routing: convert::InputRouting,
+    bitrate_kbps: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<u8, String> {
+    routing.validate()?;
+    let mut stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    let stream_id = (1..peer::MAX_SEND_TRACKS as u8)
+        .find(|id| !stream_state.extra_tracks.iter().any(|t| t.stream_id == *id))
+        .ok_or("송신 트랙 수 초과")?;
+    peer::check_packet_rate(stream_state.extra_tracks.len() + 2, stream_state.frame_duration)?;
+    let bitrate = bitrate_kbps.unwrap_or_else(|| stream_state.bitrate.load(Ordering::Relaxed)).clamp(16, 256);
+    stream_state.extra_tracks.push(peer::SendTrack::new(stream_id, input_device, routing, bitrate));
+    stream_state.extra_tracks.sort_by_key(|t| t.stream_id);
+    Ok(stream_id)
+}
+
+#[tauri::command]
+fn remove_send_track(stream_id: u8, state: State<'_, AppState>) -> Result<(), String> {
+    let mut stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    let before = stream_state.extra_tracks.len();
+    stream_state.extra_tracks.retain(|t| t.stream_id != stream_id);
+    if stream_state.extra_tracks.len() == before {
+        return Err(format!("송신 트랙 {} 없음", stream_id));
+    }
+    stream_state.dsp.remove_track(stream_id);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_send_tracks(state: State<'_, AppState>) -> Result<Vec<peer::SendTrackInfo>, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    Ok(stream_state.send_tracks().iter().map(|t| t.info()).collect())
+}
+
+#[tauri::command]
+fn set_send_track_muted(stream_id: u8, muted: bool, state: State<'_, AppState>) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    let track = stream_state.send_tracks().into_iter()
+        .find(|t| t.stream_id == stream_id)
+        .ok_or_else(|| format!("송신 트랙 {} 없음", stream_id))?;
+    track.is_muted.store(muted, Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_send_track_bitrate(stream_id: u8, bitrate_kbps: u32, state: State<'_, AppState>) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    let track = stream_state.send_tracks().into_iter()
+        .find(|t| t.stream_id == stream_id)
+        .ok_or_else(|| format!("송신 트랙 {} 없음", stream_id))?;
+    track.bitrate.store(bitrate_kbps.clamp(16, 256), Ordering::SeqCst);
+    Ok(())
+}
+
+// ===== 피어별 채널 스트립 (peer_id: P2P 주소 또는 릴레이 세션 ID) =====
+
+#[tauri::command]
+fn set_peer_gain(peer_id: String, gain_db: f32, state: State<'_, AppState>) -> Result<(), String> {
+    update_channel_strip(&state, peer_id, |strip| strip.set_gain_db(gain_db))
+}
+
+#[tauri::command]
+fn set_peer_pan(peer_id: String, pan: f32, state: State<'_, AppState>) -> Result<(), String> {
+    update_channel_strip(&state, peer_id, |strip| strip.set_pan(pan))
+}
+
+#[tauri::command]
+fn set_peer_muted(peer_id: String, muted: bool, state: State<'_, AppState>) -> Result<(), String> {
+    update_channel_strip(&state, peer_id, |strip| strip.muted = muted)
+}
+
+#[tauri::command]
+fn set_peer_solo(peer_id: String, solo: bool, state: State<'_, AppState>) -> Result<(), String> {
+    update_channel_strip(&state, peer_id, |strip| strip.solo = solo)
+}
+
+#[tauri::command]
+fn get_peer_channel_strips(state: State<'_, AppState>) -> Result<std::collections::BTreeMap<String, mixer::ChannelStrip>, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    let strips = stream_state.channel_strips.lock().map_err(|_| "채널 스트립 잠금 실패".to_string())?;
+    Ok(strips.clone())
+}
+
+fn update_channel_strip(state: &AppState, peer_id: String, change: impl FnOnce(&mut mixer::ChannelStrip)) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    let mut strips = stream_state.channel_strips.lock().map_err(|_| "채널 스트립 잠금 실패".to_string())?;
+    change(strips.entry(peer_id).or_default());
+    Ok(())
+}
+
+// ===== 로컬 모니터 =====
+
+#[tauri::command]
+fn set_monitor_enabled(enabled: bool, state: State<'_, AppState>) -> Result<(), String> {
+    state.udp_stream.lock()
+        .map_err(|_| "스트림 상태 잠금 실패".to_string())?
+        .monitor.set_enabled(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_monitor_level(gain_db: f32, state: State<'_, AppState>) -> Result<(), String> {
+    state.udp_stream.lock()
+        .map_err(|_| "스트림 상태 잠금 실패".to_string())?
+        .monitor.set_gain_db(gain_db);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_monitor_pan(pan: f32, state: State<'_, AppState>) -> Result<(), String> {
     state.udp_stream.lock()
         .map_err(|_| "스트림 상태 잠금 실패".to_string())?
-        .is_muted.store(muted, Ordering::SeqCst);
+        .monitor.set_pan(pan);
     Ok(())
 }
 
+#[tauri::command]
+fn get_monitor(state: State<'_, AppState>) -> Result<monitor::MonitorSettings, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    Ok(stream_state.monitor.settings())
+}
+
+// ===== 마스터 리미터 =====
+
+#[tauri::command]
+fn set_limiter_ceiling(ceiling_db: f32, state: State<'_, AppState>) -> Result<(), String> {
+    state.udp_stream.lock()
+        .map_err(|_| "스트림 상태 잠금 실패".to_string())?
+        .limiter.set_ceiling_db(ceiling_db);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_limiter_meter(state: State<'_, AppState>) -> Result<limiter::LimiterMeter, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    Ok(stream_state.limiter.take_meter())
+}
+
 #[tauri::command]
 fn udp_is_running(state: State<'_, AppState>) -> bool {
     state.udp_stream.lock()
@@ -127,6 +286,21 @@ fn set_audio_devices(input: Option<String>, output: Option<String>, state: State
     Ok(())
 }
 
+// 입력 채널 라우팅 (다음 스트림 시작 시 적용)
+#[tauri::command]
+fn set_input_routing(routing: convert::InputRouting, state: State<'_, AppState>) -> Result<(), String> {
+    routing.validate()?;
+    let mut stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    stream_state.input_routing = routing;
+    Ok(())
+}
+
+#[tauri::command]
+fn get_input_routing(state: State<'_, AppState>) -> Result<convert::InputRouting, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    Ok(stream_state.input_routing)
+}
+
 #[tauri::command]
 fn udp_clear_peers(state: State<'_, AppState>) -> Result<(), String> {
     state.udp_stream.lock()
@@ -135,17 +309,51 @@ fn udp_clear_peers(state: State<'_, AppState>) -> Result<(), String> {
     Ok(())
 }
 
+// 전역 최소 재생 지연 (ms); 적응 알고리즘은 이 값 아래로 내려가지 않음
+#[tauri::command]
+fn set_jitter_buffer(state: State<'_, AppState>, delay_ms: f32) -> Result<(), String> {
+    update_playout_delay(&state, |config| {
+        config.global = peer::PlayoutDelay::new(delay_ms, config.global.max_ms.max(delay_ms));
+    })
+}
+
+// 재생 지연 제약 설정 (peer_id 없으면 전역). min == max 이면 고정 지연
+#[tauri::command]
+fn set_playout_delay(state: State<'_, AppState>, peer_id: Option<String>, min_ms: f32, max_ms: f32) -> Result<(), String> {
+    let bounds = peer::PlayoutDelay::new(min_ms, max_ms);
+    update_playout_delay(&state, |config| match peer_id {
+        Some(peer) => { config.peers.insert(peer, bounds); }
+        None => config.global = bounds,
+    })
+}
+
+// 피어별 설정 제거 (전역 설정으로 복귀)
+#[tauri::command]
+fn clear_playout_delay(state: State<'_, AppState>, peer_id: String) -> Result<(), String> {
+    update_playout_delay(&state, |config| { config.peers.remove(&peer_id); })
+}
+
 #[tauri::command]
-fn set_jitter_buffer(state: State<'_, AppState>, size: usize) -> Result<(), String> {
+fn get_playout_delay(state: State<'_, AppState>) -> Result<peer::PlayoutDelayConfig, String> {
     let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    let config = stream_state.playout_delay.lock().map_err(|_| "재생 지연 설정 잠금 실패".to_string())?;
+    Ok(config.clone())
+}
+
+// 설정 변경 후 현재 지터 버퍼에 적용 (이후 생성되는 버퍼는 수신 루프가 설정을 읽음)
+fn update_playout_delay(state: &AppState, change: impl FnOnce(&mut peer::PlayoutDelayConfig)) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    let playout_delay = stream_state.playout_delay.clone();
     let jitter_buffers = stream_state.jitter_buffers.clone();
     drop(stream_state);
     
-    if let Ok(mut jb) = jitter_buffers.lock() {
-        for buffer in jb.values_mut() {
-            buffer.set_target(size.max(2).min(15)); // 20ms - 150ms
-        }
-    }
+    let config = {
+        let mut config = playout_delay.lock().map_err(|_| "재생 지연 설정 잠금 실패".to_string())?;
+        change(&mut config);
+        config.clone()
+    };
+    let mut jb = jitter_buffers.lock().map_err(|_| "지터 버퍼 잠금 실패".to_string())?;
+    config.apply(&mut jb);
     Ok(())
 }
 
@@ -159,7 +367,6 @@ fn udp_start_stream(state: State<'_, AppState>) -> Result<(), String> {
     
     let socket = stream_state.socket.clone().ok_or("소켓 없음")?;
     let peers = stream_state.peers.clone();
-    let input_device = stream_state.input_device.clone();
     let output_device = stream_state.output_device.clone();
     
     if peers.is_empty() {
@@ -167,30 +374,45 @@ fn udp_start_stream(state: State<'_, AppState>) -> Result<(), String> {
     }
     
     stream_state.is_running.store(true, Ordering::SeqCst);
+    priority::reset();
     
-    // 송신 루프 시작
-    peer::start_send_loop(
-        socket.clone(),
-        peers,
-        stream_state.is_running.clone(),
-        stream_state.is_muted.clone(),
-        stream_state.sequence.clone(),
-        stream_state.packets_sent.clone(),
-        input_device,
-    )?;
-    
-    // 수신 루프 시작
-    peer::start_recv_loop(
-        socket,
-        stream_state.is_running.clone(),
-        stream_state.jitter_buffers.clone(),
-        stream_state.playback_buffer.clone(),
-        stream_state.packets_received.clone(),
-        stream_state.peer_stats.clone(),
-        output_device,
-    )?;
-    
-    Ok(())
+    // 송신 루프 시작 (트랙마다 하나), 장치를 못 열면 이미 시작한 루프도 멈춤
+    let started = stream_state.send_tracks().into_iter().try_for_each(|track| {
+        peer::start_send_loop(
+            socket.clone(),
+            peers.clone(),
+            stream_state.is_running.clone(),
+            track,
+            stream_state.frame_duration,
+            stream_state.payload_codec,
+            stream_state.codec.clone(),
+            stream_state.dsp.clone(),
+            stream_state.echo.clone(),
+            stream_state.monitor.clone(),
+            stream_state.packets_lost.clone(),
+            stream_state.packets_received.clone(),
+        )
+    }).and_then(|_| {
+        // 수신 루프 시작
+        peer::start_recv_loop(
+            socket,
+            stream_state.is_running.clone(),
+            stream_state.jitter_buffers.clone(),
+            stream_state.playout_delay.clone(),
+            stream_state.channel_strips.clone(),
+            stream_state.limiter.clone(),
+            stream_state.monitor.clone(),
+            stream_state.echo.clone(),
+            stream_state.packets_received.clone(),
+            stream_state.packets_lost.clone(),
+            stream_state.peer_stats.clone(),
+            output_device,
+        )
+    });
+    if started.is_err() {
+        stream_state.is_running.store(false, Ordering::SeqCst);
+    }
+    started
 }
 
 #[tauri::command]
@@ -222,30 +444,39 @@ fn udp_start_relay_stream(state: State<'_, AppState>) -> Result<(), String> {
     let socket = stream_state.socket.clone().ok_or("소켓 없음")?;
     let relay_addr = stream_state.relay_addr.ok_or("릴레이 주소 없음")?;
     let session_id = stream_state.session_id.clone().ok_or("세션 ID 없음")?;
-    let input_device = stream_state.input_device.clone();
     let output_device = stream_state.output_device.clone();
     
     stream_state.is_running.store(true, Ordering::SeqCst);
+    priority::reset();
     
     // 릴레이 모드 송수신 시작
-    peer::start_relay_loop(
+    let started = peer::start_relay_loop(
         socket,
         relay_addr,
         session_id,
         stream_state.is_running.clone(),
-        stream_state.is_muted.clone(),
-        stream_state.sequence.clone(),
-        stream_state.packets_sent.clone(),
+        stream_state.send_tracks(),
+        stream_state.frame_duration,
+        stream_state.payload_codec,
+        stream_state.codec.clone(),
+        stream_state.dsp.clone(),
+        stream_state.echo.clone(),
         stream_state.packets_received.clone(),
+        stream_state.packets_lost.clone(),
         stream_state.jitter_buffers.clone(),
-        stream_state.playback_buffer.clone(),
-        input_device,
+        stream_state.playout_delay.clone(),
+        stream_state.channel_strips.clone(),
+        stream_state.limiter.clone(),
+        stream_state.monitor.clone(),
+        stream_state.peer_stats.clone(),
         output_device,
-        stream_state.input_level.clone(),
-        stream_state.bitrate.clone(),
-    )?;
-    
-    Ok(())
+        stream_state.dtx_enabled.clone(),
+        stream_state.comfort_noise.clone(),
+    );
+    if started.is_err() {
+        stream_state.is_running.store(false, Ordering::SeqCst);
+    }
+    started
 }
 
 // ===== Bitrate Control =====
@@ -261,6 +492,141 @@ fn get_bitrate(state: State<'_, AppState>) -> u32 {
     state.udp_stream.lock().unwrap().bitrate.load(Ordering::Relaxed)
 }
 
+// Opus 프레임 길이 (다음 스트림 시작부터 적용)
+#[tauri::command]
+fn set_frame_duration(frame_ms: f32, state: State<'_, AppState>) -> Result<(), String> {
+    let duration = peer::FrameDuration::from_ms(frame_ms)
+        .ok_or_else(|| format!("지원하지 않는 프레임 길이: {}ms (2.5, 5, 10, 20, 40, 60 중 선택)", frame_ms))?;
+    let mut stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    if !stream_state.payload_codec.fits(duration) {
+        return Err(format!("{:?} 코덱은 {}ms 프레임을 한 패킷에 담을 수 없음", stream_state.payload_codec, frame_ms));
+    }
+    peer::check_packet_rate(stream_state.extra_tracks.len() + 1, duration)?;
+    if let Some(preset) = codec::CodecPreset::matching(&stream_state.codec.profile()) {
+        preset.check_frame(duration)?;
+    }
+    stream_state.frame_duration = duration;
+    Ok(())
+}
+
+#[tauri::command]
+fn get_frame_duration(state: State<'_, AppState>) -> f32 {
+    state.udp_stream.lock().unwrap().frame_duration.ms()
+}
+
+// ===== Codec Profile =====
+
+// 송신 페이로드 코덱 (다음 스트림 시작부터 적용, 수신 측은 패킷 헤더로 판별)
+#[tauri::command]
+fn set_payload_codec(codec: codec::PayloadCodec, state: State<'_, AppState>) -> Result<(), String> {
+    let mut stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    if !codec.fits(stream_state.frame_duration) {
+        return Err(format!("{:?} 코덱은 {}ms 프레임을 한 패킷에 담을 수 없음", codec, stream_state.frame_duration.ms()));
+    }
+    stream_state.payload_codec = codec;
+    Ok(())
+}
+
+#[tauri::command]
+fn get_payload_codec(state: State<'_, AppState>) -> codec::PayloadCodec {
+    state.udp_stream.lock().unwrap().payload_codec
+}
+
+// 실행 중인 인코더에도 다음 프레임부터 적용
+#[tauri::command]
+fn set_codec_profile(profile: codec::CodecProfile, state: State<'_, AppState>) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    stream_state.codec.set_profile(profile);
+    Ok(())
+}
+
+#[tauri::command]
+fn apply_codec_preset(preset: codec::CodecPreset, state: State<'_, AppState>) -> Result<codec::CodecProfile, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    preset.check_frame(stream_state.frame_duration)?;
+    stream_state.codec.set_profile(preset.profile());
+    Ok(stream_state.codec.profile())
+}
+
+#[tauri::command]
+fn get_codec_profile(state: State<'_, AppState>) -> Result<codec::CodecProfile, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    Ok(stream_state.codec.profile())
+}
+
+#[tauri::command]
+fn get_codec_presets() -> Vec<codec::CodecPresetInfo> {
+    codec::CodecPreset::list()
+}
+
+// ===== 입력 처리 체인 (에코 제거, 노이즈 억제, 게이트, 하이패스, 자동 게인, 컴프레서, EQ) =====
+// 설정은 송신 트랙(stream_id)마다 따로, 0은 기본 입력
+
+#[tauri::command]
+fn set_dsp_settings(stream_id: u8, settings: dsp::DspSettings, state: State<'_, AppState>) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    check_send_track(&stream_state, stream_id)?;
+    stream_state.dsp.set_settings(stream_id, settings);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_dsp_bypass(stream_id: u8, stage: dsp::DspStage, bypass: bool, state: State<'_, AppState>) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    check_send_track(&stream_state, stream_id)?;
+    stream_state.dsp.set_bypass(stream_id, stage, bypass);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_dsp_settings(stream_id: u8, state: State<'_, AppState>) -> Result<dsp::DspSettings, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    check_send_track(&stream_state, stream_id)?;
+    Ok(stream_state.dsp.settings(stream_id))
+}
+
+#[tauri::command]
+fn start_agc_calibration(stream_id: u8, state: State<'_, AppState>) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    check_send_track(&stream_state, stream_id)?;
+    stream_state.dsp.start_agc_calibration(stream_id);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_agc_stats(stream_id: u8, state: State<'_, AppState>) -> Result<dsp::AgcStats, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    check_send_track(&stream_state, stream_id)?;
+    Ok(stream_state.dsp.agc_stats(stream_id))
+}
+
+#[tauri::command]
+fn set_echo_cancellation(enabled: bool, state: State<'_, AppState>) -> Result<(), String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    stream_state.echo.set_enabled(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_echo_cancellation_stats(state: State<'_, AppState>) -> Result<aec::EchoCancellerStats, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    Ok(stream_state.echo.stats())
+}
+
+#[tauri::command]
+fn get_noise_suppressor_stats(stream_id: u8, state: State<'_, AppState>) -> Result<denoise::NoiseSuppressorStats, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    check_send_track(&stream_state, stream_id)?;
+    Ok(stream_state.dsp.noise_suppressor_stats(stream_id))
+}
+
+fn check_send_track(stream_state: &peer::UdpStreamState, stream_id: u8) -> Result<(), String> {
+    if stream_id != 0 && !stream_state.extra_tracks.iter().any(|t| t.stream_id == stream_id) {
+        return Err(format!("송신 트랙 {} 없음", stream_id));
+    }
+    Ok(())
+}
+
 // ===== TCP Fallback Commands =====
 
 #[tauri::command]
@@ -301,13 +667,15 @@ struct UdpStats {
     peer_count: usize,
     is_running: bool,
     jitter_buffer_size: usize,
-    jitter_buffer_target: usize,
+    jitter_buffer_ms: f32,
+    jitter_buffer_target_ms: f32,
+    jitter_ms: f32,
 }
 
 #[tauri::command]
 fn get_udp_stats(state: State<'_, AppState>) -> UdpStats {
     let stream_state = state.udp_stream.lock().unwrap();
-    let sent = stream_state.packets_sent.load(Ordering::Relaxed);
+    let sent: u32 = stream_state.send_tracks().iter().map(|t| t.packets_sent.load(Ordering::Relaxed)).sum();
     let received = stream_state.packets_received.load(Ordering::Relaxed);
     let lost = stream_state.packets_lost.load(Ordering::Relaxed);
     let loss_rate = if received + lost > 0 {
@@ -316,13 +684,16 @@ fn get_udp_stats(state: State<'_, AppState>) -> UdpStats {
         0.0
     };
     
-    let (jitter_size, jitter_target) = stream_state.jitter_buffers.lock()
+    let (jitter_size, jitter_buffered, jitter_target, jitter) = stream_state.jitter_buffers.lock()
         .map(|jb| {
             let size: usize = jb.values().map(|b| b.len()).sum();
-            let target: usize = jb.values().map(|b| b.target_size()).sum();
-            (size, target)
+            // Deepest peer determines the delay we actually hear
+            let buffered = jb.values().map(|b| b.buffered_ms()).fold(0.0, f32::max);
+            let target = jb.values().map(|b| b.target_ms()).fold(0.0, f32::max);
+            let jitter = jb.values().map(|b| b.jitter_ms()).fold(0.0, f32::max);
+            (size, buffered, target, jitter)
         })
-        .unwrap_or((0, 0));
+        .unwrap_or((0, 0.0, 0.0, 0.0));
     
     UdpStats {
         packets_sent: sent,
@@ -332,7 +703,9 @@ fn get_udp_stats(state: State<'_, AppState>) -> UdpStats {
         peer_count: stream_state.peers.len(),
         is_running: stream_state.is_running.load(Ordering::Relaxed),
         jitter_buffer_size: jitter_size,
-        jitter_buffer_target: jitter_target,
+        jitter_buffer_ms: jitter_buffered,
+        jitter_buffer_target_ms: jitter_target,
+        jitter_ms: jitter,
     }
 }
 
@@ -364,6 +737,48 @@ fn get_peer_stats(state: State<'_, AppState>) -> Vec<PeerStatsResponse> {
         .unwrap_or_default()
 }
 
+#[tauri::command]
+fn get_stats_snapshot(state: State<'_, AppState>) -> Result<stats::StatsSnapshot, String> {
+    let stream_state = state.udp_stream.lock().map_err(|_| "스트림 상태 잠금 실패".to_string())?;
+    let outbound = stream_state.send_tracks().iter().map(|t| stats::OutboundStreamStats {
+        stream_id: t.stream_id,
+        bitrate_kbps: t.bitrate.load(Ordering::Relaxed),
+        fec_percent: t.fec_percent.load(Ordering::Relaxed),
+        packets_sent: t.packets_sent.load(Ordering::Relaxed),
+        bytes_sent: t.bytes_sent.load(Ordering::Relaxed),
+    }).collect();
+    let peer_stats = stream_state.peer_stats.lock().map_err(|_| "피어 통계 잠금 실패".to_string())?;
+    let jitter_buffers = stream_state.jitter_buffers.lock().map_err(|_| "지터 버퍼 잠금 실패".to_string())?;
+    Ok(stats::build_snapshot(&peer_stats, &jitter_buffers, outbound))
+}
+
+// 오디오/네트워크 스레드가 얻은 스케줄링 우선순위 (진단용)
+#[tauri::command]
+fn get_thread_priorities() -> Vec<priority::ThreadPriorityInfo> {
+    priority::report()
+}
+
+#[derive(serde::Serialize)]
+struct QualityDiagnostic {
+    network: sim::SimReport,
+    quality: quality::QualityReport,
+}
+
+// 가상 네트워크로 테스트 신호를 보내 수신 품질 측정 (preset: clean / wifi / congested)
+#[tauri::command]
+async fn run_quality_diagnostic(preset: String, seed: Option<u64>) -> Result<QualityDiagnostic, String> {
+    let config = sim::ImpairmentConfig::preset(&preset, seed.unwrap_or(1))
+        .ok_or_else(|| format!("알 수 없는 네트워크 프리셋: {}", preset))?;
+    tokio::task::spawn_blocking(move || {
+        let reference = sim::note_sequence(5.0, 1);
+        let result = sim::NetworkSimulator::new(config).run(&reference)?;
+        let quality = quality::analyze(&reference, &result.output, peer::CHANNELS);
+        Ok(QualityDiagnostic { network: result.report, quality })
+    })
+    .await
+    .map_err(|e| format!("품질 진단 실패: {}", e))?
+}
+
 // ===== Firewall Setup =====
 
 #[tauri::command]
@@ -423,13 +838,37 @@ pub fn run() {
             udp_is_running,
             udp_clear_peers,
             set_jitter_buffer,
+            set_peer_gain,
+            set_peer_pan,
+            set_peer_muted,
+            set_peer_solo,
+            get_peer_channel_strips,
+            set_limiter_ceiling,
+            get_limiter_meter,
+            set_monitor_enabled,
+            set_monitor_level,
+            set_monitor_pan,
+            get_monitor,
+            set_playout_delay,
+            clear_playout_delay,
+            get_playout_delay,
             set_audio_devices,
+            set_input_routing,
+            get_input_routing,
+            add_send_track,
+            remove_send_track,
+            get_send_tracks,
+            set_send_track_muted,
+            set_send_track_bitrate,
             udp_start_stream,
             udp_stop_stream,
             udp_set_relay,
             udp_start_relay_stream,
             get_udp_stats,
             get_peer_stats,
+            get_stats_snapshot,
+            run_quality_diagnostic,
+            get_thread_priorities,
             setup_firewall,
             // TCP fallback
             tcp_receive_audio,
@@ -438,6 +877,22 @@ pub fn run() {
             get_input_level,
             set_bitrate,
             get_bitrate,
+            set_frame_duration,
+            get_frame_duration,
+            set_payload_codec,
+            get_payload_codec,
+            set_codec_profile,
+            apply_codec_preset,
+            get_codec_profile,
+            get_codec_presets,
+            set_dsp_settings,
+            set_dsp_bypass,
+            get_dsp_settings,
+            get_noise_suppressor_stats,
+            start_agc_calibration,
+            get_agc_stats,
+            set_echo_cancellation,
+            get_echo_cancellation_stats,
         ])
         .setup(|app| {
             if cfg!(debug_assertions) {
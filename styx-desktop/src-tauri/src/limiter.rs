@@ -0,0 +1,239 @@
+// 마스터 출력 리미터 (look-ahead 피크 리미터 + 소프트 클리퍼)
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+const LOOKAHEAD_MS: f32 = 1.5;
+const RELEASE_MS: f32 = 60.0;
+const DEFAULT_CEILING_DB: f32 = -1.0;
+const MIN_CEILING_DB: f32 = -12.0;
+const MAX_CEILING_DB: f32 = 0.0;
+
+// 출력 콜백과 커맨드가 공유하는 설정/미터 (f32 비트를 원자적으로 저장)
+pub struct LimiterControl {
+    ceiling_db: AtomicU32,
+    gain_reduction_db: AtomicU32,      // Last output block
+    peak_gain_reduction_db: AtomicU32, // Largest since the last meter read
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LimiterMeter {
+    pub ceiling_db: f32,
+    pub gain_reduction_db: f32,
+    pub peak_gain_reduction_db: f32,
+}
+
+impl LimiterControl {
+    pub fn new() -> Self {
+        Self {
+            ceiling_db: AtomicU32::new(DEFAULT_CEILING_DB.to_bits()),
+            gain_reduction_db: AtomicU32::new(0),
+            peak_gain_reduction_db: AtomicU32::new(0),
+        }
+    }
+
+    pub fn set_ceiling_db(&self, ceiling_db: f32) {
+        let ceiling_db = ceiling_db.clamp(MIN_CEILING_DB, MAX_CEILING_DB);
+        self.ceiling_db.store(ceiling_db.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn ceiling_db(&self) -> f32 {
+        f32::from_bits(self.ceiling_db.load(Ordering::Relaxed))
+    }
+
+    /// Current reduction, plus the peak since the previous call (which resets it)
+    pub fn take_meter(&self) -> LimiterMeter {
+        LimiterMeter {
+            ceiling_db: self.ceiling_db(),
+            gain_reduction_db: f32::from_bits(self.gain_reduction_db.load(Ordering::Relaxed)),
+            peak_gain_reduction_db: f32::from_bits(self.peak_gain_reduction_db.swap(0, Ordering::Relaxed)),
+        }
+    }
+
+    fn report(&self, reduction_db: f32) {
+        self.gain_reduction_db.store(reduction_db.to_bits(), Ordering::Relaxed);
+        // Non-negative f32 bit patterns order like the values, so fetch_max works on them
+        self.peak_gain_reduction_db.fetch_max(reduction_db.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for LimiterControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Look-ahead peak limiter followed by a soft clipper, run in the output callback.
+///
+/// The gain each frame needs is held at its minimum over the look-ahead window, released
+/// smoothly, then box-filtered over the same window and applied to the delayed signal.
+/// The smoothed gain has fully reached every peak's value by the time that peak leaves
+/// the delay line, so the limiter never overshoots the ceiling. Everything is allocated
+/// up front; `process` does no allocation.
+pub struct MasterLimiter {
+    control: std::sync::Arc<LimiterControl>,
+    channels: usize,
+    lookahead: usize,
+    delay: Vec<f32>,              // Interleaved ring of `lookahead` frames
+    delay_pos: usize,
+    hold: VecDeque<(u64, f32)>,   // Monotonic queue for the sliding minimum
+    frame_index: u64,
+    released: f32,
+    box_ring: Vec<f32>,
+    box_pos: usize,
+    box_sum: f64,
+    release_coeff: f32,
+}
+
+impl MasterLimiter {
+    pub fn new(channels: usize, sample_rate: u32, control: std::sync::Arc<LimiterControl>) -> Self {
+        let channels = channels.max(1);
+        let lookahead = ((LOOKAHEAD_MS * sample_rate as f32 / 1000.0) as usize).max(1);
+        Self {
+            control,
+            channels,
+            lookahead,
+            delay: vec![0.0; lookahead * channels],
+            delay_pos: 0,
+            hold: VecDeque::with_capacity(lookahead + 2),
+            frame_index: 0,
+            released: 1.0,
+            box_ring: vec![1.0; lookahead],
+            box_pos: 0,
+            box_sum: lookahead as f64,
+            release_coeff: 1.0 - (-1000.0 / (RELEASE_MS * sample_rate as f32)).exp(),
+        }
+    }
+
+    /// Limit interleaved output in place
+    pub fn process(&mut self, data: &mut [f32]) {
+        let ceiling = 10f32.powf(self.control.ceiling_db() / 20.0);
+        let mut min_gain = 1.0f32;
+
+        for frame in data.chunks_exact_mut(self.channels) {
+            // Gain this frame needs to stay under the ceiling
+            let peak = frame.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            let needed = if peak > ceiling { ceiling / peak } else { 1.0 };
+
+            // Sliding minimum over the look-ahead window
+            while self.hold.back().is_some_and(|&(_, g)| g >= needed) {
+                self.hold.pop_back();
+            }
+            self.hold.push_back((self.frame_index, needed));
+            // The window spans lookahead + 1 frames so it still covers the frame leaving the delay line
+            while self.hold.front().is_some_and(|&(i, _)| i + (self.lookahead as u64) < self.frame_index) {
+                self.hold.pop_front();
+            }
+            let held = self.hold.front().map(|&(_, g)| g).unwrap_or(1.0);
+            self.frame_index += 1;
+
+            // Instant attack, smooth release
+            self.released = if held < self.released {
+                held
+            } else {
+                self.released + (held - self.released) * self.release_coeff
+            };
+
+            // Box filter across the window
+            self.box_sum += (self.released - self.box_ring[self.box_pos]) as f64;
+            self.box_ring[self.box_pos] = self.released;
+            self.box_pos = (self.box_pos + 1) % self.lookahead;
+            let gain = (self.box_sum / self.lookahead as f64).min(1.0) as f32;
+            min_gain = min_gain.min(gain);
+
+            // Swap the incoming frame with the delayed one and apply the gain
+            let base = self.delay_pos * self.channels;
+            for (c, sample) in frame.iter_mut().enumerate() {
+                let delayed = std::mem::replace(&mut self.delay[base + c], *sample);
+                *sample = soft_clip(delayed * gain, ceiling);
+            }
+            self.delay_pos = (self.delay_pos + 1) % self.lookahead;
+        }
+
+        self.control.report(-20.0 * min_gain.max(1e-6).log10());
+    }
+}
+
+// Safety stage: transparent below the ceiling, tanh knee above it, never past full scale
+fn soft_clip(x: f32, ceiling: f32) -> f32 {
+    let knee = ceiling.min(0.99);
+    let a = x.abs();
+    if a <= knee {
+        return x;
+    }
+    let range = 1.0 - knee;
+    (knee + range * ((a - knee) / range).tanh()).copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const RATE: u32 = 48_000;
+    const CHANNELS: usize = 2;
+
+    fn frames(ms: u32) -> usize {
+        (ms * RATE / 1000) as usize
+    }
+
+    // Full-scale and +6dB clicks, a +6dB tone and steps both ways, between stretches of quiet
+    fn program() -> Vec<f32> {
+        let mut out = vec![0.0; frames(100) * CHANNELS];
+        for spike in [1.0, -1.0, 2.0, -2.0] {
+            out.extend([spike, 0.0]);
+            out.extend(vec![0.0; frames(20) * CHANNELS]);
+        }
+        out.extend((0..frames(50)).flat_map(|i| [2.0 * (i as f32 * 0.13).sin(); CHANNELS]));
+        for level in [0.1, 2.0, 0.1, -2.0, 1.0, 2.0] {
+            out.extend(vec![level; frames(50) * CHANNELS]);
+        }
+        out.extend(vec![0.0; frames(500) * CHANNELS]);
+        out
+    }
+
+    #[test]
+    fn output_never_exceeds_the_ceiling() {
+        let input = program();
+        for ceiling_db in [0.0, DEFAULT_CEILING_DB, -6.0] {
+            let ceiling = 10f32.powf(ceiling_db / 20.0);
+            for block in [1, 64, 441, 512, 1024] {
+                let control = Arc::new(LimiterControl::new());
+                control.set_ceiling_db(ceiling_db);
+                let mut limiter = MasterLimiter::new(CHANNELS, RATE, control);
+                let mut output = input.clone();
+                for chunk in output.chunks_mut(block * CHANNELS) {
+                    limiter.process(chunk);
+                }
+                let peak = output.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+                assert!(peak <= ceiling * 1.00001, "ceiling {}dB, {}-frame blocks: peak {}", ceiling_db, block, peak);
+            }
+        }
+    }
+
+    #[test]
+    fn meter_reports_the_peak_reduction_then_resets() {
+        let control = Arc::new(LimiterControl::new());
+        let mut limiter = MasterLimiter::new(CHANNELS, RATE, control.clone());
+        let mut loud = vec![2.0; frames(50) * CHANNELS];
+        for chunk in loud.chunks_mut(256 * CHANNELS) {
+            limiter.process(chunk);
+        }
+        let meter = control.take_meter();
+        let expected = 20.0 * 2f32.log10() - DEFAULT_CEILING_DB;
+        assert!((meter.peak_gain_reduction_db - expected).abs() < 0.01, "peak {}dB", meter.peak_gain_reduction_db);
+        assert!((meter.gain_reduction_db - expected).abs() < 0.01, "current {}dB", meter.gain_reduction_db);
+
+        // The peak is cleared by the read; the current value releases once the signal drops
+        assert_eq!(control.take_meter().peak_gain_reduction_db, 0.0);
+        let mut quiet = vec![0.1; frames(1000) * CHANNELS];
+        for chunk in quiet.chunks_mut(256 * CHANNELS) {
+            limiter.process(chunk);
+        }
+        control.take_meter();
+        limiter.process(&mut vec![0.1; 256 * CHANNELS]);
+        let meter = control.take_meter();
+        assert!(meter.gain_reduction_db < 0.01, "current {}dB after release", meter.gain_reduction_db);
+        assert!(meter.peak_gain_reduction_db < 0.01, "peak {}dB after release", meter.peak_gain_reduction_db);
+    }
+}
@@ -0,0 +1,287 @@
+// 경량 무손실 코덱: 24비트 PCM을 채널별 고정 선형 예측 + 라이스 부호로 압축 (FLAC 고정 예측기 방식)
+use crate::peer::CHANNELS;
+use crate::stream::{dequantize, pcm_to_samples, quantize, samples_to_pcm};
+
+const BITS: u32 = 24;
+const MAX_ORDER: usize = 3;
+const MAX_RICE_PARAM: u32 = 24;
+const ORDER_BITS: u32 = 2;
+const RICE_PARAM_BITS: u32 = 5;
+
+// First payload byte
+const VERBATIM: u8 = 0;   // Plain 24-bit PCM, for audio that does not compress (noise)
+const COMPRESSED: u8 = 1;
+
+/// Largest payload for a frame of `frames` samples per channel (the verbatim case)
+pub fn max_payload_bytes(frames: usize) -> usize {
+    1 + frames * CHANNELS * (BITS / 8) as usize
+}
+
+// One channel's residual after the best fixed predictor
+struct ChannelPlan {
+    order: usize,
+    rice_param: u32,
+    warmup: Vec<i32>,
+    residuals: Vec<u32>, // Zigzag-coded
+    bits: u64,
+}
+
+/// Encode interleaved stereo. Exact for the 24-bit quantized signal.
+pub fn encode(samples: &[f32]) -> Vec<u8> {
+    let pcm: Vec<i32> = samples.iter().map(|&s| quantize(s, BITS)).collect();
+    let plans: Vec<ChannelPlan> = (0..CHANNELS)
+        .map(|c| plan_channel(&pcm.iter().skip(c).step_by(CHANNELS).copied().collect::<Vec<_>>()))
+        .collect();
+
+    let compressed_bytes = plans.iter().map(|p| p.bits).sum::<u64>().div_ceil(8) as usize + 1;
+    if compressed_bytes >= max_payload_bytes(samples.len() / CHANNELS) {
+        let mut out = vec![VERBATIM];
+        out.extend(samples_to_pcm(samples, BITS));
+        return out;
+    }
+
+    let mut writer = BitWriter::with_capacity(compressed_bytes);
+    writer.bytes.push(COMPRESSED);
+    for plan in &plans {
+        writer.write(plan.order as u32, ORDER_BITS);
+        writer.write(plan.rice_param, RICE_PARAM_BITS);
+        for &w in &plan.warmup {
+            writer.write(w as u32, BITS);
+        }
+        for &u in &plan.residuals {
+            writer.write_unary(u >> plan.rice_param);
+            writer.write(u, plan.rice_param);
+        }
+    }
+    writer.finish()
+}
+
+/// Decode a payload carrying `frames` samples per channel
+pub fn decode(data: &[u8], frames: usize) -> Result<Vec<f32>, String> {
+    match data.first() {
+        Some(&VERBATIM) if data.len() == max_payload_bytes(frames) => Ok(pcm_to_samples(&data[1..], BITS)),
+        Some(&COMPRESSED) => {
+            let mut reader = BitReader { data: &data[1..], pos: 0 };
+            let mut out = vec![0.0f32; frames * CHANNELS];
+            for c in 0..CHANNELS {
+                let order = reader.read(ORDER_BITS)? as usize;
+                let rice_param = reader.read(RICE_PARAM_BITS)?;
+                if order > frames || rice_param > MAX_RICE_PARAM {
+                    return Err("무손실 페이로드 손상".to_string());
+                }
+                let mut history = [0i64; MAX_ORDER];
+                let max = (1i64 << (BITS - 1)) - 1;
+                for n in 0..frames {
+                    let value = if n < order {
+                        (((reader.read(BITS)? << (32 - BITS)) as i32) >> (32 - BITS)) as i64
+                    } else {
+                        let u = reader.read_unary()? << rice_param | reader.read(rice_param)?;
+                        let residual = (u >> 1) as i64 ^ -((u & 1) as i64);
+                        predict(order, &history) + residual
+                    };
+                    // Valid streams never leave the quantizer's range; clamping keeps corrupt ones within full scale
+                    let value = value.clamp(-max, max);
+                    history.rotate_right(1);
+                    history[0] = value;
+                    out[n * CHANNELS + c] = dequantize(value as i32, BITS);
+                }
+            }
+            Ok(out)
+        }
+        _ => Err("무손실 페이로드 형식 오류".to_string()),
+    }
+}
+
+// Fixed polynomial predictors; history[0] is the previous sample
+fn predict(order: usize, history: &[i64; MAX_ORDER]) -> i64 {
+    match order {
+        0 => 0,
+        1 => history[0],
+        2 => 2 * history[0] - history[1],
+        _ => 3 * history[0] - 3 * history[1] + history[2],
+    }
+}
+
+fn plan_channel(samples: &[i32]) -> ChannelPlan {
+    let mut best: Option<ChannelPlan> = None;
+    for order in 0..=MAX_ORDER.min(samples.len()) {
+        let mut history = [0i64; MAX_ORDER];
+        let mut residuals = Vec::with_capacity(samples.len() - order);
+        for (n, &s) in samples.iter().enumerate() {
+            if n >= order {
+                let r = s as i64 - predict(order, &history);
+                residuals.push(((r << 1) ^ (r >> 63)) as u32);
+            }
+            history.rotate_right(1);
+            history[0] = s as i64;
+        }
+        // Each residual costs its quotient in unary, a stop bit and the low bits
+        let (rice_param, residual_bits) = (0..=MAX_RICE_PARAM)
+            .map(|k| (k, residuals.iter().map(|&u| (u >> k) as u64 + 1 + k as u64).sum::<u64>()))
+            .min_by_key(|&(_, bits)| bits)
+            .unwrap_or((0, 0));
+        let bits = (ORDER_BITS + RICE_PARAM_BITS) as u64 + order as u64 * BITS as u64 + residual_bits;
+        let better = match &best {
+            Some(b) => bits < b.bits,
+            None => true,
+        };
+        if better {
+            best = Some(ChannelPlan {
+                order,
+                rice_param,
+                warmup: samples[..order].to_vec(),
+                residuals,
+                bits,
+            });
+        }
+    }
+    best.expect("order 0 is always planned")
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    pending: u32, // Bits in acc not yet written out
+}
+
+impl BitWriter {
+    fn with_capacity(bytes: usize) -> Self {
+        Self { bytes: Vec::with_capacity(bytes), acc: 0, pending: 0 }
+    }
+
+    // Lowest `bits` bits of value, most significant first (bits <= 32)
+    fn write(&mut self, value: u32, bits: u32) {
+        self.acc = (self.acc << bits) | (value as u64 & ((1u64 << bits) - 1));
+        self.pending += bits;
+        while self.pending >= 8 {
+            self.pending -= 8;
+            self.bytes.push((self.acc >> self.pending) as u8);
+        }
+        self.acc &= (1u64 << self.pending) - 1;
+    }
+
+    // q ones and a terminating zero
+    fn write_unary(&mut self, mut q: u32) {
+        while q >= 32 {
+            self.write(u32::MAX, 32);
+            q -= 32;
+        }
+        self.write(((1u64 << q) - 1) as u32 * 2, q + 1);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.pending > 0 {
+            self.bytes.push((self.acc << (8 - self.pending)) as u8);
+        }
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize, // In bits
+}
+
+impl BitReader<'_> {
+    fn read_bit(&mut self) -> Result<u32, String> {
+        let byte = self.data.get(self.pos / 8).ok_or("무손실 페이로드가 잘림")?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Ok(bit as u32)
+    }
+
+    fn read(&mut self, bits: u32) -> Result<u32, String> {
+        let mut value = 0u32;
+        for _ in 0..bits {
+            value = (value << 1) | self.read_bit()?;
+        }
+        Ok(value)
+    }
+
+    fn read_unary(&mut self) -> Result<u32, String> {
+        let mut q = 0u32;
+        while self.read_bit()? == 1 {
+            q += 1;
+        }
+        Ok(q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peer::SAMPLE_RATE;
+    use std::f32::consts::PI;
+
+    const FRAMES: usize = 240; // 5ms
+
+    // Audio exactly representable in 24 bits, as the codec promises to return it
+    fn quantized(samples: impl Iterator<Item = f32>) -> Vec<f32> {
+        samples.take(FRAMES * CHANNELS).map(|s| dequantize(quantize(s, BITS), BITS)).collect()
+    }
+
+    fn tone(amplitude: f32) -> Vec<f32> {
+        quantized((0..).map(|i| amplitude * (2.0 * PI * 440.0 * (i / CHANNELS) as f32 / SAMPLE_RATE as f32).sin()))
+    }
+
+    // xorshift64, uniform in [-1, 1)
+    fn noise(mut state: u64) -> Vec<f32> {
+        quantized(std::iter::repeat_with(move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+        }))
+    }
+
+    #[test]
+    fn round_trip_is_bit_exact() {
+        let cases = [
+            ("silence", vec![0.0; FRAMES * CHANNELS]),
+            ("tone", tone(0.5)),
+            ("full scale", quantized((0..).map(|i| if (i / CHANNELS / 7) % 2 == 0 { 1.0 } else { -1.0 }))),
+            ("noise", noise(21)),
+        ];
+        for (name, input) in &cases {
+            let payload = encode(input);
+            assert!(payload.len() <= max_payload_bytes(FRAMES), "{}", name);
+            assert!(decode(&payload, FRAMES).expect(name) == *input, "{} not bit-exact", name);
+        }
+    }
+
+    #[test]
+    fn noise_goes_out_verbatim_and_silence_compresses() {
+        let noise = encode(&noise(21));
+        assert_eq!(noise[0], VERBATIM);
+        assert_eq!(noise.len(), max_payload_bytes(FRAMES));
+        let silence = encode(&vec![0.0; FRAMES * CHANNELS]);
+        assert_eq!(silence[0], COMPRESSED);
+        assert!(silence.len() * 8 < noise.len(), "silence took {} bytes", silence.len());
+    }
+
+    #[test]
+    fn damaged_payloads_are_rejected_or_bounded() {
+        let payload = encode(&tone(0.5));
+        assert_eq!(payload[0], COMPRESSED);
+        assert!(decode(&[], FRAMES).is_err());
+        assert!(decode(&[7], FRAMES).is_err());
+        assert!(decode(&payload[..payload.len() / 2], FRAMES).is_err());
+        // A verbatim frame must be exactly one frame long
+        assert!(decode(&[VERBATIM; 7], FRAMES).is_err());
+
+        // Flipped bits may still decode, but never to more than full scale
+        let mut state = 5u64;
+        for _ in 0..200 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let mut damaged = payload.clone();
+            let bit = (state % ((damaged.len() - 1) * 8) as u64) as usize + 8;
+            damaged[bit / 8] ^= 1 << (bit % 8);
+            if let Ok(output) = decode(&damaged, FRAMES) {
+                assert_eq!(output.len(), FRAMES * CHANNELS);
+                assert!(output.iter().all(|s| s.is_finite() && s.abs() <= 1.0));
+            }
+        }
+    }
+}
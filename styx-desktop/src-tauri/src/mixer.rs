@@ -0,0 +1,154 @@
+// 다중 피어 믹서: 출력 주기마다 피어당 한 프레임을 합산
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use crate::peer::{JitterBuffer, PeerId, CHANNELS, FRAME_SIZE, SAMPLE_RATE};
+
+const HOLD_DECAY_MS: f32 = 2.0; // A starved peer's last sample falls to silence with this time constant
+const HOLD_FLOOR: f32 = 1e-4;   // ~-80 dBFS, below which the hold is dropped
+const MIN_GAIN_DB: f32 = -60.0;
+const MAX_GAIN_DB: f32 = 12.0;
+
+// 피어별 채널 스트립 설정
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChannelStrip {
+    pub gain_db: f32,
+    pub pan: f32, // Balance, -1.0 (left) .. 1.0 (right)
+    pub muted: bool,
+    pub solo: bool,
+}
+
+impl ChannelStrip {
+    pub fn set_gain_db(&mut self, gain_db: f32) {
+        self.gain_db = gain_db.clamp(MIN_GAIN_DB, MAX_GAIN_DB);
+    }
+
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = pan.clamp(-1.0, 1.0);
+    }
+
+    // Left/right gains. Balance keeps the near side at unity so centre is unchanged
+    // and a hard pan never boosts.
+    pub(crate) fn gains(&self, any_solo: bool) -> [f32; 2] {
+        if self.muted || (any_solo && !self.solo) {
+            return [0.0; 2];
+        }
+        let gain = 10f32.powf(self.gain_db / 20.0);
+        [gain * (1.0 - self.pan).min(1.0), gain * (1.0 + self.pan).min(1.0)]
+    }
+}
+
+impl Default for ChannelStrip {
+    fn default() -> Self {
+        Self { gain_db: 0.0, pan: 0.0, muted: false, solo: false }
+    }
+}
+
+// Per-peer mixer state
+#[derive(Default)]
+struct MixerChannel {
+    active: bool,          // Produced audio in the previous period
+    hold: [f32; CHANNELS], // Last output sample, decayed while the buffer is empty
+    gains: [f32; 2],       // Strip gains reached at the end of the last period
+    primed: bool,          // Gains hold a real value (a new peer starts at its setting)
+}
+
+/// Sums one output period from every peer's jitter buffer through its channel strip.
+/// Strip changes ramp linearly across the period, so moving a fader never steps.
+///
+/// Empty buffers: a peer whose buffer runs dry never stalls the others. Its last sample
+/// decays to silence instead of cutting off (no click), it stops counting towards the
+/// headroom, and its audio fades in over one period when it returns.
+pub struct Mixer {
+    channels: BTreeMap<PeerId, MixerChannel>,
+    frame: [f32; FRAME_SIZE],
+    gain: f32, // Headroom gain applied at the end of the last period
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        Self { channels: BTreeMap::new(), frame: [0.0; FRAME_SIZE], gain: 1.0 }
+    }
+
+    /// Mix one output period into `out`. Returns false when no peer contributed.
+    pub fn mix(
+        &mut self,
+        jitter_buffers: &mut BTreeMap<PeerId, JitterBuffer>,
+        strips: &BTreeMap<PeerId, ChannelStrip>,
+        now: Instant,
+        out: &mut [f32; FRAME_SIZE],
+    ) -> bool {
+        self.channels.retain(|peer, _| jitter_buffers.contains_key(peer));
+        out.fill(0.0);
+
+        let frames = FRAME_SIZE / CHANNELS;
+        let decay = (-1000.0 / (HOLD_DECAY_MS * SAMPLE_RATE as f32)).exp();
+        let any_solo = strips.values().any(|s| s.solo);
+        let mut audible = 0usize;
+        let mut contributed = false;
+
+        for (peer, buffer) in jitter_buffers.iter_mut() {
+            let channel = self.channels.entry(peer.clone()).or_default();
+            let target = strips.get(peer).copied().unwrap_or_default().gains(any_solo);
+            if !channel.primed {
+                channel.gains = target;
+                channel.primed = true;
+            }
+            let start = channel.gains;
+            channel.gains = target;
+
+            if buffer.pull(&mut self.frame, now) {
+                let fade_in = !channel.active;
+                for (i, (o, s)) in out.chunks_exact_mut(CHANNELS).zip(self.frame.chunks_exact(CHANNELS)).enumerate() {
+                    let t = (i + 1) as f32 / frames as f32;
+                    let ramp = if fade_in { i as f32 / frames as f32 } else { 1.0 };
+                    for c in 0..CHANNELS {
+                        let gain = start[c] + (target[c] - start[c]) * t;
+                        o[c] += s[c] * gain * ramp;
+                    }
+                }
+                let last = &self.frame[FRAME_SIZE - CHANNELS..];
+                for ((h, s), g) in channel.hold.iter_mut().zip(last).zip(target) {
+                    *h = s * g;
+                }
+                channel.active = true;
+                if target.iter().any(|&g| g > 0.0) {
+                    audible += 1;
+                }
+                contributed = true;
+            } else {
+                channel.active = false;
+                if channel.hold.iter().all(|h| h.abs() < HOLD_FLOOR) {
+                    channel.hold = [0.0; CHANNELS];
+                    continue;
+                }
+                for frame in out.chunks_exact_mut(CHANNELS) {
+                    for (o, h) in frame.iter_mut().zip(channel.hold.iter_mut()) {
+                        *h *= decay;
+                        *o += *h;
+                    }
+                }
+                contributed = true;
+            }
+        }
+
+        // Headroom of 1/sqrt(audible peers), ramped so joins and dropouts don't step the level
+        let target = 1.0 / (audible.max(1) as f32).sqrt();
+        let start = self.gain;
+        for (i, frame) in out.chunks_exact_mut(CHANNELS).enumerate() {
+            let gain = start + (target - start) * (i + 1) as f32 / frames as f32;
+            for sample in frame {
+                *sample *= gain; // Overs are left for the master limiter
+            }
+        }
+        self.gain = target;
+        contributed
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,239 @@
+// 로컬 모니터: 캡처한 입력을 최소 지연으로 출력 장치에 섞음
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::mixer::ChannelStrip;
+use crate::peer::{AUDIO_RING_SAMPLES, CHANNELS, FRAME_SIZE, SAMPLE_RATE};
+use crate::resample::{DriftEstimator, FractionalResampler};
+
+const MONITOR_MARGIN_MS: f64 = 1.5;  // Lowest queue level the drift loop aims for
+const MONITOR_WINDOW_MS: f64 = 250.0; // Level minimum is taken over this long
+const MONITOR_MAX_MS: f64 = 40.0;    // Beyond this (output stalled) the queue restarts
+const MAX_BLOCK_SAMPLES: usize = 8192 * CHANNELS;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorSettings {
+    pub enabled: bool,
+    pub gain_db: f32,
+    pub pan: f32,
+}
+
+/// Monitor settings shared by the capture callbacks (one input per send track), the
+/// output callback and the commands. Audio moves through one SPSC ring per track.
+pub struct Monitor {
+    enabled: AtomicBool,
+    gain_db: AtomicU32, // f32 bits
+    pan: AtomicU32,     // f32 bits
+    pending: Mutex<Vec<(u8, HeapCons<f32>)>>, // Rings created before the output stream starts
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        let strip = ChannelStrip::default();
+        Self {
+            enabled: AtomicBool::new(false),
+            gain_db: AtomicU32::new(strip.gain_db.to_bits()),
+            pan: AtomicU32::new(strip.pan.to_bits()),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_gain_db(&self, gain_db: f32) {
+        let mut strip = self.strip();
+        strip.set_gain_db(gain_db);
+        self.gain_db.store(strip.gain_db.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_pan(&self, pan: f32) {
+        let mut strip = self.strip();
+        strip.set_pan(pan);
+        self.pan.store(strip.pan.to_bits(), Ordering::Relaxed);
+    }
+
+    // Level and pan as a channel strip; mute and solo are unused
+    fn strip(&self) -> ChannelStrip {
+        ChannelStrip {
+            gain_db: f32::from_bits(self.gain_db.load(Ordering::Relaxed)),
+            pan: f32::from_bits(self.pan.load(Ordering::Relaxed)),
+            ..ChannelStrip::default()
+        }
+    }
+
+    pub fn settings(&self) -> MonitorSettings {
+        let strip = self.strip();
+        MonitorSettings {
+            enabled: self.enabled.load(Ordering::Relaxed),
+            gain_db: strip.gain_db,
+            pan: strip.pan,
+        }
+    }
+
+    /// Capture-side handle for one send track. Create every input before calling
+    /// `output`, which takes over the rings registered so far.
+    pub fn input(self: &Arc<Self>, stream_id: u8) -> MonitorInput {
+        let (ring, queued) = HeapRb::<f32>::new(AUDIO_RING_SAMPLES).split();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.retain(|(id, _)| *id != stream_id); // Left over from a session that never played
+            pending.push((stream_id, queued));
+        }
+        MonitorInput { monitor: self.clone(), ring }
+    }
+
+    /// Per-output-stream mixing state, owned by the output callback
+    pub fn output(self: &Arc<Self>) -> MonitorOutput {
+        let sources = self
+            .pending
+            .lock()
+            .map(|mut pending| pending.drain(..).map(|(_, ring)| MonitorSource::new(ring)).collect())
+            .unwrap_or_default();
+        MonitorOutput {
+            monitor: self.clone(),
+            sources,
+            block: Vec::with_capacity(MAX_BLOCK_SAMPLES),
+            gains: [0.0; CHANNELS],
+        }
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Owned by a capture callback
+pub struct MonitorInput {
+    monitor: Arc<Monitor>,
+    ring: HeapProd<f32>,
+}
+
+impl MonitorInput {
+    /// Queue captured audio (internal format); dropped while monitoring is off
+    pub fn push(&mut self, samples: &[f32]) {
+        if self.monitor.enabled.load(Ordering::Relaxed) {
+            self.ring.push_slice(samples);
+        }
+    }
+}
+
+/// One input device's queue towards the output device. The two run on independent
+/// clocks, so the queue goes through the same drift-corrected resampler as the peer
+/// jitter buffers, aiming to keep its lowest level just above empty.
+struct MonitorSource {
+    ring: HeapCons<f32>,
+    scratch: [f32; FRAME_SIZE],
+    resampler: FractionalResampler,
+    drift: DriftEstimator,
+    started: bool,
+    window_min_ms: f64,
+    window_ms: f64,
+}
+
+impl MonitorSource {
+    fn new(ring: HeapCons<f32>) -> Self {
+        let max_frames = (MONITOR_MAX_MS * SAMPLE_RATE as f64 / 1000.0) as usize + FRAME_SIZE;
+        Self {
+            ring,
+            scratch: [0.0; FRAME_SIZE],
+            resampler: FractionalResampler::with_capacity(max_frames),
+            drift: DriftEstimator::new(),
+            started: false,
+            window_min_ms: f64::MAX,
+            window_ms: 0.0,
+        }
+    }
+
+    // Start over from empty at minimum delay
+    fn clear(&mut self) {
+        self.ring.clear();
+        self.resampler.clear();
+        self.drift = DriftEstimator::new();
+        self.started = false;
+        self.window_min_ms = f64::MAX;
+        self.window_ms = 0.0;
+    }
+
+    fn pull(&mut self, out: &mut [f32]) -> bool {
+        while !self.ring.is_empty() {
+            if self.resampler.pending_ms() > MONITOR_MAX_MS {
+                self.resampler.clear();
+                self.started = false;
+            }
+            let n = self.ring.pop_slice(&mut self.scratch);
+            self.resampler.push(&self.scratch[..n]);
+        }
+
+        let block_ms = (out.len() / CHANNELS) as f64 * 1000.0 / SAMPLE_RATE as f64;
+        if !self.started {
+            if self.resampler.pending_ms() < block_ms + MONITOR_MARGIN_MS {
+                return false;
+            }
+            self.started = true;
+        }
+        if !self.resampler.process(out, self.drift.ratio()) {
+            // Underrun: prime again from the next capture
+            self.started = false;
+            return false;
+        }
+
+        // The level just before the next capture arrives is the real safety margin
+        self.window_min_ms = self.window_min_ms.min(self.resampler.pending_ms());
+        self.window_ms += block_ms;
+        if self.window_ms >= MONITOR_WINDOW_MS {
+            self.drift.update(self.window_min_ms, MONITOR_MARGIN_MS, self.window_ms);
+            self.window_min_ms = f64::MAX;
+            self.window_ms = 0.0;
+        }
+        true
+    }
+}
+
+pub struct MonitorOutput {
+    monitor: Arc<Monitor>,
+    sources: Vec<MonitorSource>,
+    block: Vec<f32>,
+    gains: [f32; CHANNELS], // Reached at the end of the last block
+}
+
+impl MonitorOutput {
+    /// Add the monitor signal to an output block (48 kHz interleaved stereo)
+    pub fn mix_into(&mut self, out: &mut [f32]) {
+        let target = if self.monitor.enabled.load(Ordering::Relaxed) {
+            self.monitor.strip().gains(false)
+        } else {
+            [0.0; CHANNELS]
+        };
+        if target == [0.0; CHANNELS] && self.gains == [0.0; CHANNELS] {
+            return;
+        }
+
+        let len = out.len().min(MAX_BLOCK_SAMPLES);
+        let frames = len / CHANNELS;
+        let start = self.gains;
+        self.block.resize(len, 0.0);
+        for source in &mut self.sources {
+            if !source.pull(&mut self.block) {
+                continue;
+            }
+            // Level and pan changes ramp across the block, like the peer mixer
+            for (i, (o, s)) in out.chunks_exact_mut(CHANNELS).zip(self.block.chunks_exact(CHANNELS)).enumerate() {
+                let t = (i + 1) as f32 / frames as f32;
+                for c in 0..CHANNELS {
+                    o[c] += s[c] * (start[c] + (target[c] - start[c]) * t);
+                }
+            }
+        }
+        self.gains = target;
+        // Faded out: drop what is queued so re-enabling starts at minimum delay
+        if target == [0.0; CHANNELS] {
+            self.sources.iter_mut().for_each(MonitorSource::clear);
+        }
+    }
+}
@@ -1,21 +1,122 @@
 // UDP P2P 오디오 피어 모듈
-use opus::{Encoder, Decoder, Application, Channels};
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio::net::UdpSocket;
-use tokio::sync::mpsc;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use serde::{Deserialize, Serialize};
 
+use crate::aec::EchoControl;
+use crate::codec::{AudioCodec, AudioDecoder, CodecControl, CodecProfile, PayloadCodec};
+use crate::dsp::{DspControl, InputChain};
+use crate::convert::{build_input_stream, build_output_stream, choose_input_config, choose_output_config, InputConverter, InputRouting, OutputConverter};
+use crate::limiter::{LimiterControl, MasterLimiter};
+use crate::mixer::{ChannelStrip, Mixer};
+use crate::monitor::Monitor;
+use crate::priority::{self, ThreadRole};
+use crate::resample::{DriftEstimator, FractionalResampler};
+use crate::stats::PeerStats;
 use crate::udp::AudioPacketHeader;
 
-const FRAME_SIZE: usize = 480; // 5ms @ 48kHz stereo (240 samples per channel) - reduced for lower latency
+pub(crate) const SAMPLE_RATE: u32 = 48000;
+pub(crate) const CHANNELS: usize = 2;
+pub(crate) const FRAME_SIZE: usize = 480; // Mixing/playout period: 5ms @ 48kHz, 240 samples per channel interleaved as stereo
+pub(crate) const MAX_FRAME_LEN: usize = 2880 * CHANNELS; // Longest Opus frame (60ms), interleaved
 const MAX_PACKET_SIZE: usize = 1500;
-const MIN_JITTER_BUFFER: usize = 0;  // Allow zero buffer for excellent connections
-const PLAYBACK_BUFFER_CAP: usize = 9600; // 100ms @ 48kHz stereo
-const MAX_JITTER_BUFFER: usize = 20; // 100ms maximum (5ms * 20 frames)
+pub(crate) const MAX_PAYLOAD_SIZE: usize = MAX_PACKET_SIZE - AudioPacketHeader::SIZE - 20; // Leaves room for the relay session prefix
+const MIN_JITTER_DELAY_MS: f32 = 0.0;  // Allow zero buffer for excellent connections
+const MAX_JITTER_DELAY_MS: f32 = 100.0; // Default ceiling for the adaptive playout delay
+const PLAYOUT_DELAY_LIMIT_MS: f32 = 250.0; // Hard ceiling for user-set playout delay
+const MIN_BUFFER_CAPACITY_MS: f32 = 30.0;
+const PLAYOUT_MARGIN_MS: f32 = 1.0; // Headroom over one frame for the resampler look-ahead
+const PLAYOUT_POLL_MS: u64 = 2; // Network loops wake at least this often to feed playout
 const KEEPALIVE_INTERVAL_MS: u64 = 5000; // 5초마다 keepalive
+const MAX_CONCEALED_GAP: u32 = 10; // Larger gaps are treated as a stream restart
+const MAX_REORDER_DEPTH: u32 = 10; // Packets further behind the newest are a sender restart
+const REORDER_HOLD_MS: u64 = 10_000; // A seen reorder depth keeps flooring the target this long
+const MAX_RELAY_PEERS: usize = 8;
+pub(crate) const MAX_SEND_TRACKS: usize = 8; // Including the main input
+pub(crate) const RELAY_PACKET_BUDGET: u32 = 400; // Packets/s per client; the relay drops past 500/s per IP
+const DEFAULT_FEC_PERCENT: u32 = 5; // Expected loss the encoder adds FEC for
+pub(crate) const AUDIO_RING_SAMPLES: usize = SAMPLE_RATE as usize / 5 * CHANNELS; // 200ms between an audio callback and its thread
+const MAX_CAPTURE_BACKLOG: usize = FRAME_SIZE * 10; // Older capture audio is dropped when encoding falls behind
+const CAPTURE_POLL_MS: u64 = 1; // Encoder threads check the capture ring this often
+
+/// Opus frame length for a session. Shorter frames cut latency; longer ones cut the
+/// per-packet overhead (header, UDP/IP and FEC bytes) and the packet rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameDuration {
+    Ms2_5,
+    #[default]
+    Ms5,
+    Ms10,
+    Ms20,
+    Ms40,
+    Ms60,
+}
+
+impl FrameDuration {
+    const ALL: [FrameDuration; 6] = [
+        FrameDuration::Ms2_5,
+        FrameDuration::Ms5,
+        FrameDuration::Ms10,
+        FrameDuration::Ms20,
+        FrameDuration::Ms40,
+        FrameDuration::Ms60,
+    ];
+
+    /// Samples per channel at 48kHz
+    pub fn samples_per_channel(self) -> usize {
+        match self {
+            FrameDuration::Ms2_5 => 120,
+            FrameDuration::Ms5 => 240,
+            FrameDuration::Ms10 => 480,
+            FrameDuration::Ms20 => 960,
+            FrameDuration::Ms40 => 1920,
+            FrameDuration::Ms60 => 2880,
+        }
+    }
+
+    /// Interleaved samples per frame
+    pub fn frame_len(self) -> usize {
+        self.samples_per_channel() * CHANNELS
+    }
+
+    pub fn ms(self) -> f32 {
+        self.samples_per_channel() as f32 * 1000.0 / SAMPLE_RATE as f32
+    }
+
+    pub fn frames_per_second(self) -> u32 {
+        SAMPLE_RATE / self.samples_per_channel() as u32
+    }
+
+    /// One of the durations Opus accepts (2.5, 5, 10, 20, 40 or 60 ms)
+    pub fn from_ms(ms: f32) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| (d.ms() - ms).abs() < 0.01)
+    }
+
+    /// From the per-channel sample count in a packet header
+    pub fn from_samples(samples: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.samples_per_channel() == samples as usize)
+    }
+}
+
+/// Every send track is its own packet stream, so the relay's per-IP rate limit
+/// caps tracks × frames per second.
+pub(crate) fn check_packet_rate(tracks: usize, frame: FrameDuration) -> Result<(), String> {
+    let rate = tracks as u32 * frame.frames_per_second();
+    if rate > RELAY_PACKET_BUDGET {
+        return Err(format!(
+            "송신 트랙 {}개 × {}ms 프레임은 초당 {}패킷으로 릴레이 한도({})를 넘음",
+            tracks, frame.ms(), rate, RELAY_PACKET_BUDGET
+        ));
+    }
+    Ok(())
+}
 
 // Configurable buffer sizes (samples)
 pub static CPAL_BUFFER_SIZE: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(480);
@@ -52,29 +153,129 @@ fn get_best_host() -> cpal::Host {
 
 // NetEQ-style adaptive jitter buffer
 pub struct JitterBuffer {
-    buffer: BTreeMap<u32, Vec<f32>>,
+    buffer: BTreeMap<u32, BufferedFrame>,
     next_seq: u32,
-    target_size: usize,
-    // Arrival time tracking for variance calculation
-    expected_interval_ms: i64,
-    last_arrival: i64,
+    started: bool, // Playout has begun, so next_seq is meaningful
+    played_mask: u64, // Bit i set: next_seq - 1 - i was played from a real packet
+    target_delay_ms: f32,
+    delay_bounds: PlayoutDelay, // The adaptive target never leaves these
+    frame_ms: f32, // Duration of the most recently received frame
+    // RFC 3550 interarrival jitter, measured against a monotonic receive clock
+    clock_origin: Instant,
+    last_transit_us: Option<i64>,
+    last_arrival: Option<Instant>,
+    // Reordering, which the averaged jitter estimate barely registers
+    newest: Option<(u32, i64)>, // Highest sequence pushed and its transit
+    reorder_ms: f32,            // Deepest recent reorder: how much later than its successor a packet came
+    last_reorder: Option<Instant>,
     // Statistics
     late_packets: u32,
     total_packets: u32,
-    jitter_estimate: f32, // Exponential moving average of jitter
+    jitter_estimate_us: f64, // J = J + (|D| - J) / 16, in microseconds
+    in_concealment: bool,
+    stats: JitterStats,
+    // Playout at the output clock
+    resampler: FractionalResampler,
+    drift: DriftEstimator,
+}
+
+struct BufferedFrame {
+    samples: Vec<f32>,
+    concealed: bool,
+}
+
+// 지터 버퍼 누적 통계
+#[derive(Default, Clone, Copy)]
+pub struct JitterStats {
+    pub packets_late: u32,       // Arrived after their playout slot
+    pub packets_discarded: u32,  // Dropped to keep the buffer within bounds
+    pub packets_duplicated: u32, // Same sequence already buffered or played
+    pub accelerate_ops: u32,     // Frames dropped to reduce delay
+    pub expand_ops: u32,         // Concealment frames played in place of missing audio
+    pub concealed_samples: u64,  // Per channel
+    pub concealment_events: u32, // Runs of consecutive concealment
+}
+
+// 재생 지연 제약 (ms)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlayoutDelay {
+    pub min_ms: f32,
+    pub max_ms: f32,
+}
+
+impl PlayoutDelay {
+    /// Bounds within the hard limits; a max below min is raised to min
+    pub fn new(min_ms: f32, max_ms: f32) -> Self {
+        let min_ms = min_ms.clamp(MIN_JITTER_DELAY_MS, PLAYOUT_DELAY_LIMIT_MS);
+        let max_ms = max_ms.clamp(min_ms, PLAYOUT_DELAY_LIMIT_MS);
+        Self { min_ms, max_ms }
+    }
+    
+    pub fn clamp(&self, delay_ms: f32) -> f32 {
+        delay_ms.clamp(self.min_ms, self.max_ms)
+    }
+}
+
+impl Default for PlayoutDelay {
+    fn default() -> Self {
+        Self { min_ms: MIN_JITTER_DELAY_MS, max_ms: MAX_JITTER_DELAY_MS }
+    }
+}
+
+// 전역 + 피어별 재생 지연 설정 (피어별 설정이 전역보다 우선)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlayoutDelayConfig {
+    pub global: PlayoutDelay,
+    pub peers: BTreeMap<PeerId, PlayoutDelay>,
+}
+
+impl PlayoutDelayConfig {
+    pub fn for_peer(&self, peer: &str) -> PlayoutDelay {
+        self.peers.get(peer).copied().unwrap_or(self.global)
+    }
+    
+    /// Push the current constraints to every live buffer
+    pub fn apply(&self, jitter_buffers: &mut BTreeMap<PeerId, JitterBuffer>) {
+        for (peer, buffer) in jitter_buffers.iter_mut() {
+            buffer.set_delay_bounds(self.for_peer(peer));
+        }
+    }
+}
+
+// 새 피어의 지터 버퍼: 설정된 지연 제약으로 생성
+fn new_peer_buffer(playout_delay: &Mutex<PlayoutDelayConfig>, peer: &str) -> JitterBuffer {
+    let bounds = playout_delay.lock().map(|c| c.for_peer(peer)).unwrap_or_default();
+    JitterBuffer::with_delay_bounds(bounds)
+}
+
+// Wrap-around aware sequence comparison
+fn seq_before(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
 }
 
 impl JitterBuffer {
-    pub fn new(initial_size: usize) -> Self {
+    pub fn new(initial_delay_ms: f32) -> Self {
         Self { 
             buffer: BTreeMap::new(), 
             next_seq: 0, 
-            target_size: initial_size.max(MIN_JITTER_BUFFER),
-            expected_interval_ms: 5, // 5ms frame
-            last_arrival: 0,
+            started: false,
+            played_mask: 0,
+            target_delay_ms: PlayoutDelay::default().clamp(initial_delay_ms),
+            delay_bounds: PlayoutDelay::default(),
+            frame_ms: frame_duration_ms(FRAME_SIZE),
+            clock_origin: Instant::now(),
+            last_transit_us: None,
+            last_arrival: None,
+            newest: None,
+            reorder_ms: 0.0,
+            last_reorder: None,
             late_packets: 0,
             total_packets: 0,
-            jitter_estimate: 0.0,
+            jitter_estimate_us: 0.0,
+            in_concealment: false,
+            stats: JitterStats::default(),
+            resampler: FractionalResampler::new(),
+            drift: DriftEstimator::new(),
         }
     }
     
@@ -82,44 +283,82 @@ impl JitterBuffer {
         self.buffer.len()
     }
     
-    pub fn target_size(&self) -> usize {
-        self.target_size
+    /// Audio currently queued, in milliseconds
+    pub fn buffered_ms(&self) -> f32 {
+        self.buffer.len() as f32 * self.frame_ms
     }
     
-    pub fn set_target(&mut self, size: usize) {
-        self.target_size = size.max(MIN_JITTER_BUFFER).min(MAX_JITTER_BUFFER);
+    pub fn target_ms(&self) -> f32 {
+        self.target_delay_ms
     }
     
-    pub fn push(&mut self, seq: u32, samples: Vec<f32>) {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.as_millis() as i64)
-            .unwrap_or(0);
-        
+    /// Buffer for a new peer, starting at the lowest delay its bounds allow
+    pub fn with_delay_bounds(bounds: PlayoutDelay) -> Self {
+        let mut buffer = Self::new(bounds.min_ms);
+        buffer.set_delay_bounds(bounds);
+        buffer
+    }
+    
+    pub fn delay_bounds(&self) -> PlayoutDelay {
+        self.delay_bounds
+    }
+    
+    /// Constrain the adaptive target; equal bounds pin the playout delay
+    pub fn set_delay_bounds(&mut self, bounds: PlayoutDelay) {
+        self.delay_bounds = bounds;
+        self.target_delay_ms = bounds.clamp(self.target_delay_ms);
+    }
+    
+    pub fn stats(&self) -> JitterStats {
+        self.stats
+    }
+    
+    // Reorder depth rounded up to whole frames
+    fn reorder_floor_ms(&self) -> f32 {
+        (self.reorder_ms / self.frame_ms).ceil() * self.frame_ms
+    }
+    
+    // Target depth rounded up to whole frames of the current frame size
+    fn target_frames(&self) -> usize {
+        (self.target_delay_ms / self.frame_ms).ceil() as usize
+    }
+    
+    /// Queue a decoded frame. `timestamp_us` is the sender's media timestamp and
+    /// `arrival` the local monotonic receive time.
+    pub fn push(&mut self, seq: u32, timestamp_us: u64, samples: Vec<f32>, arrival: Instant) {
         self.total_packets += 1;
         
-        // Track inter-arrival jitter (RFC 3550 style)
-        if self.last_arrival > 0 {
-            let interval = now - self.last_arrival;
-            let deviation = (interval - self.expected_interval_ms).abs() as f32;
-            // Exponential moving average: jitter = jitter + (|D| - jitter) / 16
-            self.jitter_estimate += (deviation - self.jitter_estimate) / 16.0;
+        // RFC 3550 6.4.1: D(i,j) = (Rj - Ri) - (Sj - Si), J += (|D(i,j)| - J) / 16
+        let arrival_us = arrival.saturating_duration_since(self.clock_origin).as_micros() as i64;
+        let transit_us = arrival_us.wrapping_sub(timestamp_us as i64);
+        if let Some(last_transit) = self.last_transit_us {
+            let d = transit_us.wrapping_sub(last_transit).unsigned_abs() as f64;
+            // A jump of more than a second means the sender restarted its clock
+            if d < 1_000_000.0 {
+                self.jitter_estimate_us += (d - self.jitter_estimate_us) / 16.0;
+            }
         }
-        self.last_arrival = now;
+        self.last_transit_us = Some(transit_us);
+        self.last_arrival = Some(arrival);
         
-        // Too old packet (already played)
-        if self.next_seq > 0 && seq < self.next_seq.wrapping_sub(self.target_size as u32 * 2) {
-            self.late_packets += 1;
-            return;
+        if !samples.is_empty() {
+            self.frame_ms = frame_duration_ms(samples.len());
         }
         
-        // Prevent buffer overflow
-        while self.buffer.len() >= self.target_size * 2 {
-            if let Some(&oldest) = self.buffer.keys().next() {
-                self.buffer.remove(&oldest);
+        // A packet overtaken by a later one needs the buffer to cover how much later it came.
+        // One far behind is a sender restart and becomes the new reference.
+        match self.newest {
+            Some((newest, newest_transit)) if (1..MAX_REORDER_DEPTH).contains(&newest.wrapping_sub(seq)) => {
+                let behind_ms = transit_us.wrapping_sub(newest_transit) as f32 / 1000.0;
+                if behind_ms > 0.0 && behind_ms < PLAYOUT_DELAY_LIMIT_MS {
+                    self.reorder_ms = self.reorder_ms.max(behind_ms);
+                    self.last_reorder = Some(arrival);
+                    self.target_delay_ms = self.delay_bounds.clamp(self.target_delay_ms.max(self.reorder_floor_ms()));
+                }
             }
+            _ => self.newest = Some((seq, transit_us)),
         }
-        self.buffer.insert(seq, samples);
+        self.insert(seq, BufferedFrame { samples, concealed: false });
         
         // Adaptive sizing every 50 packets
         if self.total_packets % 50 == 0 {
@@ -127,68 +366,449 @@ impl JitterBuffer {
         }
     }
     
+    /// Queue a locally concealed frame; it does not count towards jitter statistics
+    /// and never replaces a real frame that arrived out of order.
+    pub fn push_concealed(&mut self, seq: u32, samples: Vec<f32>) {
+        self.insert(seq, BufferedFrame { samples, concealed: true });
+    }
+    
+    /// Queue a frame rebuilt from the next packet's in-band FEC
+    pub fn push_recovered(&mut self, seq: u32, samples: Vec<f32>) {
+        if self.buffer.get(&seq).is_some_and(|f| !f.concealed) { return; }
+        self.insert(seq, BufferedFrame { samples, concealed: false });
+    }
+    
+    fn insert(&mut self, seq: u32, frame: BufferedFrame) {
+        // Too old packet (already played)
+        if self.started && seq_before(seq, self.next_seq) {
+            if !frame.concealed {
+                let age = self.next_seq.wrapping_sub(seq).wrapping_sub(1);
+                if age < 64 && self.played_mask & (1u64 << age) != 0 {
+                    self.stats.packets_duplicated += 1;
+                } else {
+                    self.stats.packets_late += 1;
+                    self.late_packets += 1;
+                }
+            }
+            return;
+        }
+        
+        // A real frame replaces concealment, never the other way round
+        if let Some(existing) = self.buffer.get(&seq) {
+            if frame.concealed { return; }
+            if !existing.concealed {
+                self.stats.packets_duplicated += 1;
+                return;
+            }
+        }
+        
+        // Prevent buffer overflow: drop the oldest frames to cut delay. The floor leaves
+        // room for a burst of concealment frames even at zero target delay, counted in
+        // frames too so that long frames still fit a whole concealable gap.
+        let min_frames = ((MIN_BUFFER_CAPACITY_MS / self.frame_ms).ceil() as usize).max(MAX_CONCEALED_GAP as usize);
+        let capacity = (self.target_frames() * 2).max(min_frames);
+        while !self.buffer.is_empty() && self.buffer.len() >= capacity {
+            if let Some(&oldest) = self.buffer.keys().next() {
+                self.buffer.remove(&oldest);
+                self.stats.packets_discarded += 1;
+                self.stats.accelerate_ops += 1;
+            }
+        }
+        self.buffer.insert(seq, frame);
+    }
+    
     fn adapt_size(&mut self) {
         if self.total_packets == 0 { return; }
         
         let late_ratio = self.late_packets as f32 / self.total_packets as f32;
+        let jitter_ms = self.jitter_ms();
         
-        // NetEQ-style: target = 2 * jitter_estimate / frame_duration
-        let jitter_frames = (self.jitter_estimate / self.expected_interval_ms as f32 * 2.0).ceil() as usize;
-        let jitter_target = jitter_frames.max(MIN_JITTER_BUFFER).min(MAX_JITTER_BUFFER);
+        // NetEQ-style: target = 2 * jitter_estimate, rounded up to whole frames
+        let bounds = self.delay_bounds;
+        let jitter_target = bounds.clamp((jitter_ms * 2.0 / self.frame_ms).ceil() * self.frame_ms);
+        let step = self.frame_ms;
         
         // Blend late packet ratio with jitter estimate
         if late_ratio > 0.03 {
             // >3% late → increase buffer
-            self.target_size = (self.target_size + 1).min(MAX_JITTER_BUFFER);
-        } else if late_ratio < 0.001 && self.jitter_estimate < 2.0 {
-            // Excellent connection (<0.1% late, <2ms jitter) → allow zero buffer
-            self.target_size = 0;
-        } else if late_ratio < 0.005 && self.target_size > jitter_target {
+            self.target_delay_ms += step;
+        } else if late_ratio < 0.001 && jitter_ms < 2.0 {
+            // Excellent connection (<0.1% late, <2ms jitter) → lowest allowed delay
+            self.target_delay_ms = bounds.min_ms;
+        } else if late_ratio < 0.005 && self.target_delay_ms > jitter_target {
             // <0.5% late and above jitter target → decrease
-            self.target_size = self.target_size.saturating_sub(1);
+            self.target_delay_ms -= step;
         } else {
             // Slowly converge to jitter-based target
-            if self.target_size < jitter_target {
-                self.target_size += 1;
-            } else if self.target_size > jitter_target + 2 {
-                self.target_size = self.target_size.saturating_sub(1);
+            if self.target_delay_ms < jitter_target {
+                self.target_delay_ms += step;
+            } else if self.target_delay_ms > jitter_target + 2.0 * step {
+                self.target_delay_ms -= step;
             }
         }
         
+        let reorder_expired = match (self.last_reorder, self.last_arrival) {
+            (Some(reorder), Some(arrival)) => arrival.saturating_duration_since(reorder) > std::time::Duration::from_millis(REORDER_HOLD_MS),
+            _ => true,
+        };
+        if reorder_expired {
+            self.reorder_ms = 0.0;
+            self.last_reorder = None;
+        }
+        self.target_delay_ms = bounds.clamp(self.target_delay_ms.max(self.reorder_floor_ms()));
+        
         // Reset stats
         self.late_packets = 0;
         self.total_packets = 0;
     }
     
     pub fn pop(&mut self) -> Option<Vec<f32>> {
+        // Prefill to the target before playout starts
+        if !self.started && self.buffered_ms() < self.target_delay_ms {
+            return None;
+        }
+        
         // Normal sequence packet
-        if let Some(samples) = self.buffer.remove(&self.next_seq) {
-            self.next_seq = self.next_seq.wrapping_add(1);
-            return Some(samples);
+        if let Some(frame) = self.buffer.remove(&self.next_seq) {
+            self.advance(1);
+            return Some(self.account(frame));
         }
         
         // If buffer is sufficiently full, return oldest (skip sequence)
-        if self.buffer.len() >= self.target_size / 2 {
-            if let Some(&seq) = self.buffer.keys().next() {
-                self.next_seq = seq.wrapping_add(1);
-                return self.buffer.remove(&seq);
+        if !self.buffer.is_empty() && self.buffered_ms() >= self.target_delay_ms / 2.0 {
+            if let Some((seq, frame)) = self.buffer.pop_first() {
+                let skipped = if self.started { seq.wrapping_sub(self.next_seq) } else { 0 };
+                self.next_seq = seq;
+                self.played_mask = self.played_mask.checked_shl(skipped).unwrap_or(0);
+                self.advance(1);
+                return Some(self.account(frame));
             }
         }
         None
     }
     
+    fn advance(&mut self, frames: u32) {
+        self.next_seq = self.next_seq.wrapping_add(frames);
+        self.played_mask = self.played_mask.checked_shl(frames).unwrap_or(0);
+        self.started = true;
+    }
+    
+    // Record how a popped frame was produced
+    fn account(&mut self, frame: BufferedFrame) -> Vec<f32> {
+        if frame.concealed {
+            self.stats.expand_ops += 1;
+            self.stats.concealed_samples += (frame.samples.len() / CHANNELS) as u64;
+            if !self.in_concealment {
+                self.stats.concealment_events += 1;
+                self.in_concealment = true;
+            }
+        } else {
+            self.played_mask |= 1;
+            self.in_concealment = false;
+        }
+        frame.samples
+    }
+    
+    /// Fill `out` (interleaved, one output period) at the playback clock. Frames are
+    /// resampled by the drift estimate so the buffer level holds at the target instead of
+    /// creeping until it overflows or runs dry. Returns false on underrun.
+    pub fn pull(&mut self, out: &mut [f32], now: Instant) -> bool {
+        let frames = out.len() / CHANNELS;
+        let ratio = self.drift.ratio();
+        while !self.resampler.can_produce(frames, ratio) {
+            match self.pop() {
+                Some(samples) => self.resampler.push(&samples),
+                None => break,
+            }
+        }
+        let produced = self.resampler.process(out, ratio);
+        
+        if self.started {
+            // Sender media queued minus what we consumed. Packets land in whole frames, so
+            // the time since the last arrival fills in the staircase; without it a slow
+            // sender only shows up as a full-frame step every few seconds.
+            let since_arrival_ms = self.last_arrival
+                .map(|t| now.saturating_duration_since(t).as_secs_f32() * 1000.0)
+                .unwrap_or(0.0)
+                .min(self.frame_ms);
+            let level_ms = self.buffered_ms() as f64 + self.resampler.pending_ms() + since_arrival_ms as f64;
+            let setpoint_ms = (self.target_delay_ms + self.frame_ms + PLAYOUT_MARGIN_MS) as f64;
+            let elapsed_ms = frames as f64 * 1000.0 / SAMPLE_RATE as f64;
+            self.drift.update(level_ms, setpoint_ms, elapsed_ms);
+        }
+        produced
+    }
+    
+    /// Estimated sender clock offset relative to our playback clock
+    pub fn drift_ppm(&self) -> f32 {
+        self.drift.drift_ppm() as f32
+    }
+    
+    /// Interarrival jitter estimate in milliseconds
     pub fn jitter_ms(&self) -> f32 {
-        self.jitter_estimate
+        (self.jitter_estimate_us / 1000.0) as f32
+    }
+}
+
+// Duration of an interleaved stereo frame at 48kHz
+fn frame_duration_ms(samples: usize) -> f32 {
+    (samples / CHANNELS) as f32 * 1000.0 / SAMPLE_RATE as f32
+}
+
+// Sender media timestamp (microseconds) for a running per-channel sample count
+pub(crate) fn media_timestamp_us(samples_per_channel: u64) -> u64 {
+    samples_per_channel * 1_000_000 / SAMPLE_RATE as u64
+}
+
+// 피어 식별자: P2P는 소켓 주소, 릴레이는 송신자 세션 ID
+pub type PeerId = String;
+
+// 트랙별 피어 ID: 기본 트랙(0)은 송신자 ID 그대로, 추가 트랙은 "송신자#n"
+pub fn track_peer_id(sender: &str, stream_id: u8) -> PeerId {
+    if stream_id == 0 {
+        sender.to_string()
+    } else {
+        format!("{}#{}", sender, stream_id)
+    }
+}
+
+// 로컬 송신 트랙: 입력 소스, 인코더 설정, 시퀀스 공간이 트랙마다 독립
+#[derive(Clone)]
+pub struct SendTrack {
+    pub stream_id: u8,
+    pub input_device: Option<String>,
+    pub input_routing: InputRouting,
+    pub is_muted: Arc<AtomicBool>,
+    pub sequence: Arc<AtomicU32>,
+    pub bitrate: Arc<AtomicU32>,     // Opus bitrate in kbps
+    pub fec_percent: Arc<AtomicU32>,
+    pub input_level: Arc<AtomicU32>, // 0-100
+    pub packets_sent: Arc<AtomicU32>,
+    pub bytes_sent: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SendTrackInfo {
+    pub stream_id: u8,
+    pub input_device: Option<String>,
+    pub input_routing: InputRouting,
+    pub muted: bool,
+    pub bitrate_kbps: u32,
+    pub fec_percent: u32,
+    pub input_level: u32,
+}
+
+impl SendTrack {
+    pub fn new(stream_id: u8, input_device: Option<String>, input_routing: InputRouting, bitrate_kbps: u32) -> Self {
+        Self {
+            stream_id,
+            input_device,
+            input_routing,
+            is_muted: Arc::new(AtomicBool::new(false)),
+            sequence: Arc::new(AtomicU32::new(0)),
+            bitrate: Arc::new(AtomicU32::new(bitrate_kbps)),
+            fec_percent: Arc::new(AtomicU32::new(0)),
+            input_level: Arc::new(AtomicU32::new(0)),
+            packets_sent: Arc::new(AtomicU32::new(0)),
+            bytes_sent: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn info(&self) -> SendTrackInfo {
+        SendTrackInfo {
+            stream_id: self.stream_id,
+            input_device: self.input_device.clone(),
+            input_routing: self.input_routing,
+            muted: self.is_muted.load(Ordering::Relaxed),
+            bitrate_kbps: self.bitrate.load(Ordering::Relaxed),
+            fec_percent: self.fec_percent.load(Ordering::Relaxed),
+            input_level: self.input_level.load(Ordering::Relaxed),
+        }
     }
 }
 
-// 피어별 통계
-#[derive(Default, Clone)]
-pub struct PeerStats {
-    pub packets_received: u32,
-    pub packets_lost: u32,
-    pub last_seq: u32,
-    pub audio_level: f32,
+// 송신자별 수신 파이프라인 (P2P/릴레이 공용): 손실 감지 → PLC → 디코딩 → 지터 버퍼
+pub struct PeerReceiver {
+    decoder: Box<dyn AudioDecoder>, // Replaced when the sender's codec changes
+    highest_seq: Option<u32>,
+    last_packet: Instant,
+}
+
+pub struct ReceivedFrame {
+    pub lost: u32,
+    pub fec_recovered: bool,
+    pub bytes: usize,
+    pub level: f32,
+}
+
+impl PeerReceiver {
+    pub fn new() -> Result<Self, String> {
+        Ok(Self {
+            decoder: PayloadCodec::default().decoder()?,
+            highest_seq: None,
+            last_packet: Instant::now(),
+        })
+    }
+    
+    pub fn last_packet(&self) -> Instant {
+        self.last_packet
+    }
+    
+    pub fn receive(
+        &mut self,
+        jitter: &mut JitterBuffer,
+        header: &AudioPacketHeader,
+        payload: &[u8],
+        arrival: Instant,
+    ) -> Result<ReceivedFrame, String> {
+        self.last_packet = arrival;
+        let frame = FrameDuration::from_samples(header.frame_samples)
+            .ok_or_else(|| format!("지원하지 않는 프레임 길이: {}", header.frame_samples))?;
+        let codec = PayloadCodec::from_id(header.codec)
+            .ok_or_else(|| format!("지원하지 않는 코덱: {}", header.codec))?;
+        if codec != self.decoder.codec() {
+            self.decoder = codec.decoder()?;
+        }
+        
+        // 패킷 손실 감지 (wrap-around 처리, 순서가 뒤바뀐 패킷은 손실로 세지 않음)
+        let mut lost = 0u32;
+        match self.highest_seq {
+            Some(highest) => {
+                let gap = header.sequence.wrapping_sub(highest);
+                if gap != 0 && gap < u32::MAX / 2 {
+                    lost = gap - 1;
+                    self.highest_seq = Some(header.sequence);
+                }
+            }
+            None => self.highest_seq = Some(header.sequence),
+        }
+        
+        // 손실된 패킷은 PLC로 채우고, 직전 프레임은 이 패킷의 in-band FEC로 복구
+        let mut fec_recovered = false;
+        if lost > 0 && lost < MAX_CONCEALED_GAP {
+            let first_missing = header.sequence.wrapping_sub(lost);
+            for i in 0..lost - 1 {
+                if let Ok(mut plc_samples) = self.decoder.conceal(frame) {
+                    // Apply fade-out for consecutive losses
+                    let fade_factor = plc_fade_factor(i);
+                    if fade_factor < 1.0 {
+                        for sample in plc_samples.iter_mut() {
+                            *sample *= fade_factor;
+                        }
+                    }
+                    jitter.push_concealed(first_missing.wrapping_add(i), plc_samples);
+                }
+            }
+            let previous = header.sequence.wrapping_sub(1);
+            match self.decoder.decode_fec(payload, frame) {
+                Some(fec_samples) => {
+                    jitter.push_recovered(previous, fec_samples);
+                    fec_recovered = true;
+                }
+                None => {
+                    if let Ok(plc_samples) = self.decoder.conceal(frame) {
+                        jitter.push_concealed(previous, plc_samples);
+                    }
+                }
+            }
+        }
+        
+        let samples = self.decoder.decode(payload, frame)?;
+        let level = calculate_audio_level(&samples);
+        jitter.push(header.sequence, header.timestamp, samples, arrival);
+        
+        Ok(ReceivedFrame {
+            lost,
+            fec_recovered,
+            bytes: AudioPacketHeader::SIZE + payload.len(),
+            level,
+        })
+    }
+}
+
+// 연속 손실 시 PLC 페이드 아웃
+fn plc_fade_factor(consecutive: u32) -> f32 {
+    match consecutive {
+        0 => 1.0,
+        1 => 0.8,
+        2 => 0.5,
+        3 => 0.3,
+        _ => 0.1,
+    }
+}
+
+// 피어별 통계 업데이트
+fn record_peer_packet(
+    peer_stats: &Mutex<BTreeMap<PeerId, PeerStats>>,
+    peer: &PeerId,
+    sequence: u32,
+    frame: &ReceivedFrame,
+) {
+    if let Ok(mut stats) = peer_stats.lock() {
+        let s = stats.entry(peer.clone()).or_default();
+        s.packets_received += 1;
+        s.packets_lost += frame.lost;
+        s.bytes_received += frame.bytes as u64;
+        if frame.fec_recovered {
+            s.fec_recovered += 1;
+        }
+        s.last_seq = sequence;
+        s.audio_level = frame.level;
+    }
+}
+
+// Cleanup stale streams (keep max 8 peers' worth), with everything kept per stream
+fn evict_stale_stream(
+    receivers: &mut BTreeMap<PeerId, PeerReceiver>,
+    jitter_buffers: &Mutex<BTreeMap<PeerId, JitterBuffer>>,
+    peer_stats: &Mutex<BTreeMap<PeerId, PeerStats>>,
+) {
+    if receivers.len() <= MAX_RELAY_PEERS * MAX_SEND_TRACKS {
+        return;
+    }
+    let stalest = receivers.iter()
+        .min_by_key(|(_, r)| r.last_packet())
+        .map(|(id, _)| id.clone());
+    if let Some(id) = stalest {
+        receivers.remove(&id);
+        if let Ok(mut jb) = jitter_buffers.lock() {
+            jb.remove(&id);
+        }
+        if let Ok(mut stats) = peer_stats.lock() {
+            stats.remove(&id);
+        }
+    }
+}
+
+// 재생 버퍼 하한: 출력 콜백 한 번 분량 + 한 프레임 여유
+fn playback_low_water() -> usize {
+    (get_cpal_buffer_size() as usize * CHANNELS).max(FRAME_SIZE) + FRAME_SIZE
+}
+
+// 출력 장치 소비 속도에 맞춰 믹서 출력으로 재생 링 버퍼 채움
+fn mix_into_playback(
+    mixer: &mut Mixer,
+    jitter_buffers: &Mutex<BTreeMap<PeerId, JitterBuffer>>,
+    channel_strips: &Mutex<BTreeMap<PeerId, ChannelStrip>>,
+    playback: &mut HeapProd<f32>,
+) {
+    let low_water = playback_low_water();
+    let mut period = [0.0f32; FRAME_SIZE];
+    let now = Instant::now();
+    let strips = channel_strips.lock().map(|s| s.clone()).unwrap_or_default();
+    let Ok(mut jb) = jitter_buffers.lock() else { return };
+    while playback.occupied_len() < low_water && mixer.mix(&mut jb, &strips, now, &mut period) {
+        playback.push_slice(&period);
+    }
+}
+
+// 인코더가 밀리면 오래된 캡처 오디오를 버려 지연이 쌓이지 않게 함 (긴 프레임은 두 개까지 유지)
+fn trim_capture_backlog(captured: &mut HeapCons<f32>, frame_len: usize) {
+    let keep = MAX_CAPTURE_BACKLOG.max(frame_len * 2);
+    let backlog = captured.occupied_len();
+    if backlog > keep {
+        captured.skip(backlog - keep);
+    }
 }
 
 // UDP 스트림 상태
@@ -198,17 +818,27 @@ pub struct UdpStreamState {
     pub is_running: Arc<AtomicBool>,
     pub is_muted: Arc<AtomicBool>,
     pub sequence: Arc<AtomicU32>,
-    pub jitter_buffers: Arc<Mutex<BTreeMap<SocketAddr, JitterBuffer>>>,
-    pub playback_buffer: Arc<Mutex<VecDeque<f32>>>, // VecDeque for O(1) pop_front
-    // 통계
+    pub jitter_buffers: Arc<Mutex<BTreeMap<PeerId, JitterBuffer>>>,
+    pub playout_delay: Arc<Mutex<PlayoutDelayConfig>>, // Kept across sessions
+    pub channel_strips: Arc<Mutex<BTreeMap<PeerId, ChannelStrip>>>, // Per-peer mixer settings
+    pub limiter: Arc<LimiterControl>, // Master limiter ceiling and metering
+    pub monitor: Arc<Monitor>, // Local input monitoring
+    pub codec: Arc<CodecControl>, // Encoder profile shared by every send track
+    pub dsp: Arc<DspControl>,     // Input processing settings shared by every send track
+    pub echo: Arc<EchoControl>,   // Echo cancellation (opt-in) and its render reference
+    // 통계 (송신 카운터는 기본 트랙 몫, 추가 트랙은 각자 가짐)
     pub packets_sent: Arc<AtomicU32>,
+    pub bytes_sent: Arc<AtomicU64>,
     pub packets_received: Arc<AtomicU32>,
     pub packets_lost: Arc<AtomicU32>,
-    pub peer_stats: Arc<Mutex<BTreeMap<SocketAddr, PeerStats>>>,
+    pub peer_stats: Arc<Mutex<BTreeMap<PeerId, PeerStats>>>,
     pub input_level: Arc<AtomicU32>, // 0-100 input level
     pub bitrate: Arc<AtomicU32>, // Opus bitrate in kbps
+    pub fec_percent: Arc<AtomicU32>, // Expected loss the encoder currently protects against
     // 장치 선택
     pub input_device: Option<String>,
+    pub input_routing: InputRouting, // Hardware channels feeding the sent stream
+    pub extra_tracks: Vec<SendTrack>, // Additional send tracks (stream ID 1 and up)
     pub output_device: Option<String>,
     // 릴레이 모드
     pub relay_addr: Option<SocketAddr>,
@@ -216,6 +846,8 @@ pub struct UdpStreamState {
     // Optional audio features
     pub dtx_enabled: Arc<AtomicBool>,      // Discontinuous transmission (save bandwidth during silence)
     pub comfort_noise: Arc<AtomicBool>,    // Generate comfort noise during silence
+    pub frame_duration: FrameDuration,     // Codec frame length, applied when a stream starts
+    pub payload_codec: PayloadCodec,       // Codec for sent streams, applied when a stream starts
 }
 
 impl Default for UdpStreamState {
@@ -227,24 +859,53 @@ impl Default for UdpStreamState {
             is_muted: Arc::new(AtomicBool::new(false)),
             sequence: Arc::new(AtomicU32::new(0)),
             jitter_buffers: Arc::new(Mutex::new(BTreeMap::new())),
-            playback_buffer: Arc::new(Mutex::new(VecDeque::new())),
+            playout_delay: Arc::new(Mutex::new(PlayoutDelayConfig::default())),
+            channel_strips: Arc::new(Mutex::new(BTreeMap::new())),
+            limiter: Arc::new(LimiterControl::new()),
+            monitor: Arc::new(Monitor::new()),
+            codec: Arc::new(CodecControl::new()),
+            dsp: Arc::new(DspControl::new()),
+            echo: Arc::new(EchoControl::new()),
             packets_sent: Arc::new(AtomicU32::new(0)),
+            bytes_sent: Arc::new(AtomicU64::new(0)),
             packets_received: Arc::new(AtomicU32::new(0)),
             packets_lost: Arc::new(AtomicU32::new(0)),
             peer_stats: Arc::new(Mutex::new(BTreeMap::new())),
             input_level: Arc::new(AtomicU32::new(0)),
             bitrate: Arc::new(AtomicU32::new(96)), // 96kbps default
+            fec_percent: Arc::new(AtomicU32::new(0)),
             input_device: None,
+            input_routing: InputRouting::Auto,
+            extra_tracks: Vec::new(),
             output_device: None,
             relay_addr: None,
             session_id: None,
             dtx_enabled: Arc::new(AtomicBool::new(false)),  // Off by default
             comfort_noise: Arc::new(AtomicBool::new(false)), // Off by default
+            frame_duration: FrameDuration::default(),
+            payload_codec: PayloadCodec::default(),
         }
     }
 }
 
 impl UdpStreamState {
+    /// Every local send track; stream 0 is the main input and shares the top-level state
+    pub fn send_tracks(&self) -> Vec<SendTrack> {
+        let main = SendTrack {
+            stream_id: 0,
+            input_device: self.input_device.clone(),
+            input_routing: self.input_routing,
+            is_muted: self.is_muted.clone(),
+            sequence: self.sequence.clone(),
+            bitrate: self.bitrate.clone(),
+            fec_percent: self.fec_percent.clone(),
+            input_level: self.input_level.clone(),
+            packets_sent: self.packets_sent.clone(),
+            bytes_sent: self.bytes_sent.clone(),
+        };
+        std::iter::once(main).chain(self.extra_tracks.iter().cloned()).collect()
+    }
+    
     /// Cleanup resources and stop all operations
     #[allow(dead_code)]
     pub fn cleanup(&mut self) {
@@ -255,15 +916,15 @@ impl UdpStreamState {
         if let Ok(mut jb) = self.jitter_buffers.lock() {
             jb.clear();
         }
-        if let Ok(mut pb) = self.playback_buffer.lock() {
-            pb.clear();
-        }
         if let Ok(mut stats) = self.peer_stats.lock() {
             stats.clear();
         }
         
         // Reset counters
-        self.packets_sent.store(0, Ordering::Relaxed);
+        for track in self.send_tracks() {
+            track.packets_sent.store(0, Ordering::Relaxed);
+            track.bytes_sent.store(0, Ordering::Relaxed);
+        }
         self.packets_received.store(0, Ordering::Relaxed);
         self.packets_lost.store(0, Ordering::Relaxed);
         self.input_level.store(0, Ordering::Relaxed);
@@ -276,51 +937,22 @@ impl UdpStreamState {
     }
 }
 
-// Opus 인코더/디코더 생성
-pub fn create_encoder_with_bitrate(bitrate_kbps: u32) -> Result<Encoder, String> {
-    let mut encoder = Encoder::new(48000, Channels::Stereo, Application::LowDelay)
-        .map_err(|e| format!("Opus 인코더 생성 실패: {:?}", e))?;
-    encoder.set_bitrate(opus::Bitrate::Bits(bitrate_kbps as i32 * 1000)).ok();
-    encoder.set_inband_fec(true).ok();
-    encoder.set_packet_loss_perc(5).ok();
-    encoder.set_vbr(false).ok(); // CBR for consistent latency
+// 세션 코덱 인코더 생성 (Opus는 기본 FEC 손실률로 시작)
+pub fn create_encoder(codec: PayloadCodec, frame: FrameDuration, profile: &CodecProfile, bitrate_kbps: u32) -> Result<Box<dyn AudioCodec>, String> {
+    let mut encoder = codec.encoder(profile, bitrate_kbps)?;
+    if !encoder.supports_frame(frame) {
+        return Err(format!("{:?} 코덱은 {}ms 프레임을 한 패킷에 담을 수 없습니다", codec, frame.ms()));
+    }
+    encoder.set_packet_loss_perc(DEFAULT_FEC_PERCENT).ok();
     Ok(encoder)
 }
 
-pub fn create_encoder() -> Result<Encoder, String> {
-    create_encoder_with_bitrate(96)
-}
-
-pub fn create_decoder() -> Result<Decoder, String> {
-    Decoder::new(48000, Channels::Stereo)
-        .map_err(|e| format!("Opus 디코더 생성 실패: {:?}", e))
-}
-
-// 오디오 프레임 인코딩/디코딩
-pub fn encode_frame(encoder: &mut Encoder, samples: &[f32]) -> Result<Vec<u8>, String> {
-    let mut output = vec![0u8; MAX_PACKET_SIZE];
-    let len = encoder.encode_float(samples, &mut output)
-        .map_err(|e| format!("인코딩 실패: {:?}", e))?;
-    output.truncate(len);
-    Ok(output)
-}
-
-pub fn decode_frame(decoder: &mut Decoder, data: &[u8]) -> Result<Vec<f32>, String> {
-    let mut pcm = vec![0f32; FRAME_SIZE]; // 960 samples for stereo
-    let len = decoder.decode_float(data, &mut pcm, true) // true = FEC 활성화
-        .map_err(|e| format!("디코딩 실패: {:?}", e))?;
-    pcm.truncate(len);
-    Ok(pcm)
-}
-
-// 패킷 손실 시 PLC (Packet Loss Concealment)
-/// Decode with Opus built-in PLC (Packet Loss Concealment)
-pub fn decode_plc(decoder: &mut Decoder) -> Result<Vec<f32>, String> {
-    let mut pcm = vec![0f32; FRAME_SIZE];
-    let len = decoder.decode_float(&[], &mut pcm, true)
-        .map_err(|e| format!("PLC 실패: {:?}", e))?;
-    pcm.truncate(len);
-    Ok(pcm)
+// Adaptive FEC: expected loss for the encoder from observed loss, None until we have data
+pub fn adaptive_fec_percent(lost: u32, received: u32) -> Option<u32> {
+    if received == 0 { return None; }
+    let loss_pct = (lost as f32 / (received + lost) as f32 * 100.0).min(100.0);
+    // Add headroom for burst loss: FEC% = loss * 1.5 + 5 (minimum 5%)
+    Some(((loss_pct * 1.5 + 5.0) as u32).clamp(DEFAULT_FEC_PERCENT, 50))
 }
 
 // 오디오 레벨 계산 (RMS)
@@ -335,40 +967,62 @@ pub fn start_send_loop(
     socket: Arc<UdpSocket>,
     peers: Vec<SocketAddr>,
     is_running: Arc<AtomicBool>,
-    is_muted: Arc<AtomicBool>,
-    sequence: Arc<AtomicU32>,
-    packets_sent: Arc<AtomicU32>,
+    track: SendTrack,
+    frame_duration: FrameDuration,
+    payload_codec: PayloadCodec,
+    codec: Arc<CodecControl>,
+    dsp: Arc<DspControl>,
+    echo: Arc<EchoControl>,
+    monitor: Arc<Monitor>,
     packets_lost: Arc<AtomicU32>,
     packets_received: Arc<AtomicU32>,
-    input_device_name: Option<String>,
 ) -> Result<(), String> {
+    let SendTrack { stream_id, input_device, input_routing, is_muted, sequence, bitrate, fec_percent, input_level, packets_sent, bytes_sent } = track;
     let host = get_best_host();
-    let device = match &input_device_name {
+    let device = match &input_device {
         Some(name) => host.input_devices()
             .map_err(|e| e.to_string())?
             .find(|d| d.name().map(|n| n == *name).unwrap_or(false))
             .ok_or_else(|| format!("입력 장치 '{}' 없음", name))?,
         None => host.default_input_device().ok_or("기본 입력 장치 없음")?,
     };
-    let config = device.default_input_config().map_err(|e| e.to_string())?;
+    let supported = choose_input_config(&device, input_routing)?;
+    let sample_format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    eprintln!("[AUDIO] Input: {} Hz, {} ch, {}", config.sample_rate.0, config.channels, sample_format);
+    let mut converter = InputConverter::new(&config, input_routing)?;
     
-    let (tx, mut rx) = mpsc::channel::<Vec<f32>>(32);
+    // Capture callback → encoder, lock-free and preallocated
+    let (mut capture, mut captured) = HeapRb::<f32>::new(AUDIO_RING_SAMPLES).split();
+    let mut monitor_input = monitor.input(stream_id);
+    let mut echo_canceller = echo.canceller(stream_id);
     let is_running_capture = is_running.clone();
     let is_running_stream = is_running.clone();
     let is_running_keepalive = is_running.clone();
+    let is_running_encode = is_running.clone();
     let is_muted_clone = is_muted.clone();
+    let (mut callback_priority, callback_promoter) = priority::callback_thread(ThreadRole::Capture);
     
     // 오디오 캡처 스레드
     std::thread::spawn(move || {
-        let stream = device.build_input_stream(
-            &config.into(),
-            move |data: &[f32], _| {
-                if !is_muted_clone.load(Ordering::Relaxed) && is_running_capture.load(Ordering::Relaxed) {
-                    let _ = tx.blocking_send(data.to_vec());
+        let stream = build_input_stream(
+            &device,
+            &config,
+            sample_format,
+            move |data| {
+                callback_priority.enter();
+                // Capture keeps flowing while muted so the media clock does not stall
+                if is_running_capture.load(Ordering::Relaxed) {
+                    let muted = is_muted_clone.load(Ordering::Relaxed);
+                    converter.process(data, |samples| {
+                        if !muted {
+                            monitor_input.push(samples);
+                        }
+                        capture.push_slice(samples);
+                    });
                 }
             },
             |e| eprintln!("입력 오류: {}", e),
-            None,
         );
         match stream {
             Ok(s) => {
@@ -378,6 +1032,7 @@ pub fn start_send_loop(
                     return;
                 }
                 while is_running_stream.load(Ordering::Relaxed) {
+                    callback_promoter.poll();
                     std::thread::sleep(std::time::Duration::from_millis(10));
                 }
             }
@@ -393,82 +1048,120 @@ pub fn start_send_loop(
     let socket_clone = socket.clone();
     let peers_clone = peers.clone();
     
-    // Keepalive 태스크 (NAT 매핑 유지)
-    rt.spawn(async move {
-        let keepalive_packet = [0u8; 1]; // 빈 keepalive 패킷
-        while is_running_keepalive.load(Ordering::Relaxed) {
-            for peer in &peers_clone {
-                let _ = socket_clone.send_to(&keepalive_packet, peer).await;
+    // Keepalive 태스크 (NAT 매핑 유지, 소켓을 공유하므로 기본 트랙만)
+    if stream_id == 0 {
+        rt.spawn(async move {
+            let keepalive_packet = [0u8; 1]; // 빈 keepalive 패킷
+            while is_running_keepalive.load(Ordering::Relaxed) {
+                for peer in &peers_clone {
+                    let _ = socket_clone.send_to(&keepalive_packet, peer).await;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(KEEPALIVE_INTERVAL_MS)).await;
             }
-            tokio::time::sleep(std::time::Duration::from_millis(KEEPALIVE_INTERVAL_MS)).await;
-        }
-    });
+        });
+    }
     
-    rt.spawn(async move {
-        let mut encoder = match create_encoder() {
+    // 인코딩/전송: 우선순위를 올린 전용 스레드에서 실행 (소켓 I/O는 런타임이 구동)
+    std::thread::spawn(move || rt.block_on(async move {
+        priority::promote_current_thread(ThreadRole::Send);
+        let mut codec_generation = codec.generation();
+        let mut encoder = match create_encoder(payload_codec, frame_duration, &codec.profile(), bitrate.load(Ordering::Relaxed)) {
             Ok(e) => e,
-            Err(_) => return,
+            Err(e) => { eprintln!("[AUDIO] Encoder creation failed: {}", e); return; }
         };
-        let mut frame_buffer = Vec::with_capacity(FRAME_SIZE);
+        fec_percent.store(DEFAULT_FEC_PERCENT, Ordering::Relaxed);
+        let mut input_chain = InputChain::new(dsp, stream_id);
+        // Capture arrives in callback-sized pieces; the encoder only ever sees whole codec frames
+        let frame_len = frame_duration.frame_len();
+        let fec_update_frames = frame_duration.frames_per_second();
+        let mut frame_buf = [0.0f32; MAX_FRAME_LEN];
+        let mut media_samples = 0u64; // Per-channel samples sent, drives the media timestamp
         let mut frame_count = 0u32;
         let mut last_loss_update = 0u32;
+        let mut was_muted = is_muted.load(Ordering::Relaxed);
         
-        while let Some(samples) = rx.recv().await {
-            frame_buffer.extend(samples);
-            
-            // Prevent frame buffer from growing too large (max 10 frames)
-            if frame_buffer.len() > FRAME_SIZE * 10 {
-                frame_buffer.drain(..FRAME_SIZE); // Drop oldest frame
+        while is_running_encode.load(Ordering::Relaxed) {
+            if captured.occupied_len() < frame_len {
+                tokio::time::sleep(std::time::Duration::from_millis(CAPTURE_POLL_MS)).await;
+                continue;
             }
+            trim_capture_backlog(&mut captured, frame_len);
             
-            while frame_buffer.len() >= FRAME_SIZE {
-                let frame: Vec<f32> = frame_buffer.drain(..FRAME_SIZE).collect();
+            while captured.occupied_len() >= frame_len {
+                let frame = &mut frame_buf[..frame_len];
+                captured.pop_slice(frame);
+                // Whatever the mute did to capture, the reference may no longer line up with it
+                let muted = is_muted.load(Ordering::Relaxed);
+                if muted != was_muted {
+                    was_muted = muted;
+                    echo_canceller.reset();
+                }
+                echo_canceller.process(frame);
+                input_chain.process(frame);
+                input_level.store((calculate_audio_level(frame) * 200.0).min(100.0) as u32, Ordering::Relaxed);
+                
+                // Codec profile changed from the UI: apply it to the running encoder
+                if codec.generation() != codec_generation {
+                    codec_generation = codec.generation();
+                    if let Err(e) = encoder.apply_profile(&codec.profile()) {
+                        eprintln!("[AUDIO] Codec profile not applied: {}", e);
+                    }
+                }
                 
-                // Adaptive FEC: update every 200 frames (~1 second)
+                // Adaptive FEC: update about once a second
                 frame_count += 1;
-                if frame_count - last_loss_update >= 200 {
+                if frame_count - last_loss_update >= fec_update_frames {
                     let lost = packets_lost.load(Ordering::Relaxed);
                     let recv = packets_received.load(Ordering::Relaxed);
-                    if recv > 0 {
-                        let loss_pct = (lost as f32 / (recv + lost) as f32 * 100.0).min(100.0);
-                        // Add headroom for burst loss: FEC% = loss * 1.5 + 5 (minimum 5%)
-                        let fec_pct = ((loss_pct * 1.5 + 5.0) as i32).max(5).min(50);
+                    if let Some(fec_pct) = adaptive_fec_percent(lost, recv) {
                         encoder.set_packet_loss_perc(fec_pct).ok();
+                        fec_percent.store(fec_pct, Ordering::Relaxed);
                     }
                     last_loss_update = frame_count;
                 }
                 
-                if let Ok(opus_data) = encode_frame(&mut encoder, &frame) {
+                // Media clock advances with capture, even while muted
+                let timestamp = media_timestamp_us(media_samples);
+                media_samples += frame_duration.samples_per_channel() as u64;
+                
+                if muted {
+                    continue;
+                }
+                
+                if let Ok(payload) = encoder.encode(frame) {
+                    if encoder.is_silent_packet(&payload) {
+                        continue; // Codec DTX: nothing worth sending
+                    }
                     let seq = sequence.fetch_add(1, Ordering::SeqCst);
-                    let timestamp = std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .map(|d| d.as_micros() as u64)
-                        .unwrap_or(0);
                     let header = AudioPacketHeader {
                         sequence: seq,
                         timestamp,
                         sample_rate: 48000,
                         channels: 2,
+                        stream_id,
+                        codec: encoder.codec().id(),
+                        frame_samples: frame_duration.samples_per_channel() as u16,
                         payload_len: {
-                            let len = opus_data.len();
+                            let len = payload.len();
                             if len > u16::MAX as usize {
-                                eprintln!("CRITICAL: Opus data too large: {} bytes", len);
+                                eprintln!("CRITICAL: Payload too large: {} bytes", len);
                                 continue;
                             }
                             len as u16
                         },
                     };
                     let mut packet = header.to_bytes();
-                    packet.extend(&opus_data);
+                    packet.extend(&payload);
                     
                     for peer in &peers {
                         let _ = socket.send_to(&packet, peer).await;
                         packets_sent.fetch_add(1, Ordering::Relaxed);
+                        bytes_sent.fetch_add(packet.len() as u64, Ordering::Relaxed);
                     }
                 }
             }
         }
-    });
+    }));
     
     Ok(())
 }
@@ -477,10 +1170,15 @@ pub fn start_send_loop(
 pub fn start_recv_loop(
     socket: Arc<UdpSocket>,
     is_running: Arc<AtomicBool>,
-    jitter_buffers: Arc<Mutex<BTreeMap<SocketAddr, JitterBuffer>>>,
-    playback_buffer: Arc<Mutex<VecDeque<f32>>>,
+    jitter_buffers: Arc<Mutex<BTreeMap<PeerId, JitterBuffer>>>,
+    playout_delay: Arc<Mutex<PlayoutDelayConfig>>,
+    channel_strips: Arc<Mutex<BTreeMap<PeerId, ChannelStrip>>>,
+    limiter: Arc<LimiterControl>,
+    monitor: Arc<Monitor>,
+    echo: Arc<EchoControl>,
     packets_received: Arc<AtomicU32>,
-    peer_stats: Arc<Mutex<BTreeMap<SocketAddr, PeerStats>>>,
+    packets_lost: Arc<AtomicU32>,
+    peer_stats: Arc<Mutex<BTreeMap<PeerId, PeerStats>>>,
     output_device_name: Option<String>,
 ) -> Result<(), String> {
     let host = get_best_host();
@@ -491,24 +1189,37 @@ pub fn start_recv_loop(
             .ok_or_else(|| format!("출력 장치 '{}' 없음", name))?,
         None => host.default_output_device().ok_or("기본 출력 장치 없음")?,
     };
-    let config = device.default_output_config().map_err(|e| e.to_string())?;
+    let supported = choose_output_config(&device)?;
+    let sample_format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    eprintln!("[AUDIO] Output: {} Hz, {} ch, {}", config.sample_rate.0, config.channels, sample_format);
     
-    let playback_clone = playback_buffer.clone();
+    // Mixer → output callback, lock-free and preallocated
+    let (mut mixed, mut playback) = HeapRb::<f32>::new(AUDIO_RING_SAMPLES).split();
     let is_running_clone = is_running.clone();
+    let mut output = OutputConverter::new(&config);
+    let mut monitor_out = monitor.output();
+    let mut echo_reference = echo.render_tap();
+    let mut master = MasterLimiter::new(config.channels as usize, config.sample_rate.0, limiter);
+    let (mut callback_priority, callback_promoter) = priority::callback_thread(ThreadRole::Playback);
     
     // 오디오 재생 스레드
     std::thread::spawn(move || {
-        let stream = device.build_output_stream(
-            &config.into(),
-            move |data: &mut [f32], _| {
-                if let Ok(mut buf) = playback_clone.lock() {
-                    for sample in data.iter_mut() {
-                        *sample = buf.pop_front().unwrap_or(0.0);
-                    }
-                }
+        let stream = build_output_stream(
+            &device,
+            &config,
+            sample_format,
+            move |data| {
+                callback_priority.enter();
+                output.fill(data, |internal| {
+                    let got = playback.pop_slice(internal);
+                    internal[got..].fill(0.0);
+                    echo_reference.push(internal); // The far end only; the monitor is our own input
+                    monitor_out.mix_into(internal);
+                });
+                master.process(data);
             },
             |e| eprintln!("출력 오류: {}", e),
-            None,
         );
         match stream {
             Ok(s) => {
@@ -518,6 +1229,7 @@ pub fn start_recv_loop(
                     return;
                 }
                 while is_running_clone.load(Ordering::Relaxed) {
+                    callback_promoter.poll();
                     std::thread::sleep(std::time::Duration::from_millis(10));
                 }
             }
@@ -528,16 +1240,17 @@ pub fn start_recv_loop(
         }
     });
     
-    // UDP 수신 태스크
+    // UDP 수신/믹싱: 우선순위를 올린 전용 스레드에서 실행
     let rt = tokio::runtime::Handle::current();
-    rt.spawn(async move {
-        let mut decoders: BTreeMap<SocketAddr, Decoder> = BTreeMap::new();
-        let mut last_seq: BTreeMap<SocketAddr, u32> = BTreeMap::new();
+    std::thread::spawn(move || rt.block_on(async move {
+        priority::promote_current_thread(ThreadRole::Receive);
+        let mut receivers: BTreeMap<PeerId, PeerReceiver> = BTreeMap::new();
+        let mut mixer = Mixer::new();
         let mut buf = [0u8; MAX_PACKET_SIZE];
         
         while is_running.load(Ordering::Relaxed) {
             match tokio::time::timeout(
-                std::time::Duration::from_millis(100),
+                std::time::Duration::from_millis(PLAYOUT_POLL_MS),
                 socket.recv_from(&mut buf)
             ).await {
                 Ok(Ok((len, addr))) => {
@@ -562,64 +1275,13 @@ pub fn start_recv_loop(
                     }
                     
                     let payload = &buf[AudioPacketHeader::SIZE..len];
+                    let peer_id = track_peer_id(&addr.to_string(), header.stream_id);
                     
-                    packets_received.fetch_add(1, Ordering::Relaxed);
-                    
-                    // 패킷 손실 감지 및 per-peer 통계 업데이트
-                    let mut lost_count = 0u32;
-                    if let Some(&prev_seq) = last_seq.get(&addr) {
-                        let expected = prev_seq.wrapping_add(1);
-                        if header.sequence != expected && header.sequence > expected {
-                            // 손실된 패킷 수 (FEC로 복구 시도)
-                            let lost = header.sequence.wrapping_sub(expected);
-                            lost_count = lost;
-                            if lost < 10 { // 합리적인 범위 내에서만
-                                // Try to create decoder if needed
-                                if !decoders.contains_key(&addr) {
-                                    match create_decoder() {
-                                        Ok(decoder) => {
-                                            decoders.insert(addr, decoder);
-                                        }
-                                        Err(_) => {
-                                            eprintln!("Failed to create decoder for PLC, skipping");
-                                        }
-                                    }
-                                }
-                                
-                                if let Some(decoder) = decoders.get_mut(&addr) {
-                                    for i in 0..lost {
-                                        if let Ok(mut plc_samples) = decode_plc(decoder) {
-                                            // Apply fade-out for consecutive losses
-                                            let fade_factor = match i {
-                                                0 => 1.0,
-                                                1 => 0.8,
-                                                2 => 0.5,
-                                                3 => 0.3,
-                                                _ => 0.1,
-                                            };
-                                            if fade_factor < 1.0 {
-                                                for sample in plc_samples.iter_mut() {
-                                                    *sample *= fade_factor;
-                                                }
-                                            }
-                                            if let Ok(mut jb) = jitter_buffers.lock() {
-                                                jb.entry(addr)
-                                                    .or_insert_with(|| JitterBuffer::new(MIN_JITTER_BUFFER))
-                                                    .push(expected, plc_samples);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    last_seq.insert(addr, header.sequence);
-                    
-                    // Try to get existing decoder or create new one
-                    if !decoders.contains_key(&addr) {
-                        match create_decoder() {
-                            Ok(decoder) => {
-                                decoders.insert(addr, decoder);
+                    // Try to get existing receiver or create new one
+                    if !receivers.contains_key(&peer_id) {
+                        match PeerReceiver::new() {
+                            Ok(receiver) => {
+                                receivers.insert(peer_id.clone(), receiver);
                             }
                             Err(_) => {
                                 eprintln!("Failed to create decoder for audio, skipping packet");
@@ -628,24 +1290,17 @@ pub fn start_recv_loop(
                         }
                     }
                     
-                    if let Some(decoder) = decoders.get_mut(&addr) {
-                        if let Ok(samples) = decode_frame(decoder, payload) {
-                            // Update per-peer stats
-                            if let Ok(mut stats) = peer_stats.lock() {
-                                let s = stats.entry(addr).or_default();
-                                s.packets_received += 1;
-                                s.packets_lost += lost_count;
-                                s.last_seq = header.sequence;
-                                s.audio_level = calculate_audio_level(&samples);
-                            }
-                            
-                            if let Ok(mut jb) = jitter_buffers.lock() {
-                                jb.entry(addr)
-                                    .or_insert_with(|| JitterBuffer::new(MIN_JITTER_BUFFER))
-                                    .push(header.sequence, samples);
-                            }
+                    if let (Some(receiver), Ok(mut jb)) = (receivers.get_mut(&peer_id), jitter_buffers.lock()) {
+                        let jitter = jb.entry(peer_id.clone())
+                            .or_insert_with(|| new_peer_buffer(&playout_delay, &peer_id));
+                        // Counted once decoded, like the per-peer stats and the relay loop
+                        if let Ok(frame) = receiver.receive(jitter, &header, payload, Instant::now()) {
+                            packets_received.fetch_add(1, Ordering::Relaxed);
+                            packets_lost.fetch_add(frame.lost, Ordering::Relaxed);
+                            record_peer_packet(&peer_stats, &peer_id, header.sequence, &frame);
                         }
                     }
+                    evict_stale_stream(&mut receivers, &jitter_buffers, &peer_stats);
                 }
                 Ok(Err(e)) => {
                     eprintln!("Network error in UDP receive: {}", e);
@@ -656,22 +1311,9 @@ pub fn start_recv_loop(
                 }
             }
             
-            // 지터 버퍼에서 재생 버퍼로 이동
-            if let Ok(mut jb) = jitter_buffers.lock() {
-                for (_, buffer) in jb.iter_mut() {
-                    if let Some(samples) = buffer.pop() {
-                        if let Ok(mut pb) = playback_buffer.lock() {
-                            pb.extend(samples);
-                            // 버퍼 오버플로우 방지 (100ms 최대)
-                            while pb.len() > 9600 { // 100ms @ 48kHz stereo = 9,600 samples
-                                pb.pop_front();
-                            }
-                        }
-                    }
-                }
-            }
+            mix_into_playback(&mut mixer, &jitter_buffers, &channel_strips, &mut mixed);
         }
-    });
+    }));
     
     Ok(())
 }
@@ -683,28 +1325,32 @@ pub fn start_relay_loop(
     relay_addr: SocketAddr,
     session_id: String,
     is_running: Arc<AtomicBool>,
-    is_muted: Arc<AtomicBool>,
-    sequence: Arc<AtomicU32>,
-    packets_sent: Arc<AtomicU32>,
+    tracks: Vec<SendTrack>,
+    frame_duration: FrameDuration,
+    payload_codec: PayloadCodec,
+    codec: Arc<CodecControl>,
+    dsp: Arc<DspControl>,
+    echo: Arc<EchoControl>,
     packets_received: Arc<AtomicU32>,
-    _jitter_buffers: Arc<Mutex<BTreeMap<SocketAddr, JitterBuffer>>>,
-    playback_buffer: Arc<Mutex<VecDeque<f32>>>,
-    input_device: Option<String>,
+    packets_lost: Arc<AtomicU32>,
+    jitter_buffers: Arc<Mutex<BTreeMap<PeerId, JitterBuffer>>>,
+    playout_delay: Arc<Mutex<PlayoutDelayConfig>>,
+    channel_strips: Arc<Mutex<BTreeMap<PeerId, ChannelStrip>>>,
+    limiter: Arc<LimiterControl>,
+    monitor: Arc<Monitor>,
+    peer_stats: Arc<Mutex<BTreeMap<PeerId, PeerStats>>>,
     output_device: Option<String>,
-    input_level: Arc<AtomicU32>,
-    bitrate: Arc<AtomicU32>,
     dtx_enabled: Arc<AtomicBool>,
     comfort_noise: Arc<AtomicBool>,
 ) -> Result<(), String> {
     eprintln!("[RELAY] Starting relay loop with proper networking");
     
     let session_bytes = session_id.as_bytes().to_vec();
-    let bitrate_kbps = bitrate.load(Ordering::Relaxed);
     
     // Create a single shared socket for both send and receive
     let std_socket = std::net::UdpSocket::bind("0.0.0.0:0")
         .map_err(|e| format!("Failed to create UDP socket: {}", e))?;
-    std_socket.set_read_timeout(Some(std::time::Duration::from_millis(10))).ok();
+    std_socket.set_read_timeout(Some(std::time::Duration::from_millis(PLAYOUT_POLL_MS))).ok();
     std_socket.set_write_timeout(Some(std::time::Duration::from_millis(10))).ok();
     
     // Send initial registration packet to relay server
@@ -719,144 +1365,227 @@ pub fn start_relay_loop(
     }
     
     let std_socket = Arc::new(std_socket);
-    let std_socket_send = std_socket.clone();
     let std_socket_recv = std_socket.clone();
     
-    // Audio input thread with UDP sending
-    let is_running_send = is_running.clone();
-    let is_muted_send = is_muted.clone();
-    let sequence_send = sequence.clone();
-    let packets_sent_send = packets_sent.clone();
-    let input_level_send = input_level.clone();
-    let dtx_enabled_send = dtx_enabled.clone();
-    let session_send = session_bytes.clone();
-    
-    std::thread::spawn(move || {
-        let mut encoder = match create_encoder_with_bitrate(bitrate_kbps) {
-            Ok(e) => e,
-            Err(e) => { eprintln!("[AUDIO] Encoder creation failed: {}", e); return; }
-        };
+    // Audio input threads with UDP sending, one per local track
+    for track in tracks {
+        let is_running_send = is_running.clone();
+        let is_muted_send = track.is_muted.clone();
+        let sequence_send = track.sequence.clone();
+        let packets_sent_send = track.packets_sent.clone();
+        let bytes_sent_send = track.bytes_sent.clone();
+        let input_level_send = track.input_level.clone();
+        let dtx_enabled_send = dtx_enabled.clone();
+        let session_send = session_bytes.clone();
+        let std_socket_send = std_socket.clone();
+        let bitrate_kbps = track.bitrate.load(Ordering::Relaxed);
+        let fec_percent = track.fec_percent.clone();
+        let input_device = track.input_device.clone();
+        let input_routing = track.input_routing;
+        let stream_id = track.stream_id;
+        let is_muted_capture = track.is_muted.clone();
+        let codec = codec.clone();
+        let dsp = dsp.clone();
+        // Registered now, before the output thread below takes over the monitor and echo rings
+        let mut monitor_input = monitor.input(stream_id);
+        let mut echo_canceller = echo.canceller(stream_id);
         
-        let host = get_best_host();
-        let device = input_device
-            .and_then(|name| host.input_devices().ok()?.find(|d| d.name().ok().as_ref() == Some(&name)))
-            .or_else(|| host.default_input_device());
+        std::thread::spawn(move || {
+            priority::promote_current_thread(ThreadRole::Send);
+            let mut codec_generation = codec.generation();
+            let mut encoder = match create_encoder(payload_codec, frame_duration, &codec.profile(), bitrate_kbps) {
+                Ok(e) => e,
+                Err(e) => { eprintln!("[AUDIO] Encoder creation failed: {}", e); return; }
+            };
+            fec_percent.store(DEFAULT_FEC_PERCENT, Ordering::Relaxed);
+            let mut input_chain = InputChain::new(dsp, stream_id);
         
-        let device = match device {
-            Some(d) => d,
-            None => { eprintln!("[AUDIO] No input device"); return; }
-        };
+            let host = get_best_host();
+            let device = input_device
+                .and_then(|name| host.input_devices().ok()?.find(|d| d.name().ok().as_ref() == Some(&name)))
+                .or_else(|| host.default_input_device());
         
-        let config = cpal::StreamConfig {
-            channels: 2, // Stereo input
-            sample_rate: cpal::SampleRate(48000),
-            buffer_size: cpal::BufferSize::Fixed(get_cpal_buffer_size()), // Configurable
-        };
+            let device = match device {
+                Some(d) => d,
+                None => { eprintln!("[AUDIO] No input device"); return; }
+            };
         
-        let (tx, rx) = std::sync::mpsc::channel::<Vec<f32>>();
+            let supported = match choose_input_config(&device, input_routing) {
+                Ok(c) => c,
+                Err(e) => { eprintln!("[AUDIO] No usable input config: {}", e); return; }
+            };
+            let sample_format = supported.sample_format();
+            let mut config: cpal::StreamConfig = supported.into();
+            config.buffer_size = cpal::BufferSize::Fixed(get_cpal_buffer_size()); // Configurable
+            eprintln!("[AUDIO] Input: {} Hz, {} ch, {}", config.sample_rate.0, config.channels, sample_format);
+            let mut converter = match InputConverter::new(&config, input_routing) {
+                Ok(c) => c,
+                Err(e) => { eprintln!("[AUDIO] Input routing failed: {}", e); return; }
+            };
         
-        let stream = match device.build_input_stream(
-            &config,
-            move |data: &[f32], _| { let _ = tx.send(data.to_vec()); },
-            |e| eprintln!("[AUDIO] Input error: {}", e),
-            None,
-        ) {
-            Ok(s) => s,
-            Err(e) => { eprintln!("[AUDIO] Input stream creation failed: {}", e); return; }
-        };
+            // Capture callback → encoder, lock-free and preallocated
+            let (mut capture, mut captured) = HeapRb::<f32>::new(AUDIO_RING_SAMPLES).split();
+            let (mut callback_priority, callback_promoter) = priority::callback_thread(ThreadRole::Capture);
         
-        if let Err(e) = stream.play() {
-            eprintln!("[AUDIO] Input stream start failed: {}", e);
-            return;
-        }
+            let stream = match build_input_stream(
+                &device,
+                &config,
+                sample_format,
+                move |data| {
+                    callback_priority.enter();
+                    let muted = is_muted_capture.load(Ordering::Relaxed);
+                    converter.process(data, |samples| {
+                        if !muted {
+                            monitor_input.push(samples);
+                        }
+                        capture.push_slice(samples);
+                    });
+                },
+                |e| eprintln!("[AUDIO] Input error: {}", e),
+            ) {
+                Ok(s) => s,
+                Err(e) => { eprintln!("[AUDIO] Input stream creation failed: {}", e); return; }
+            };
         
-        // Keep stream alive
-        let _stream = stream;
+            if let Err(e) = stream.play() {
+                eprintln!("[AUDIO] Input stream start failed: {}", e);
+                return;
+            }
         
-        let mut packet_buffer = Vec::with_capacity(1024);
-        let mut padded_session = [0u8; 20];
-        let copy_len = session_send.len().min(20);
-        padded_session[..copy_len].copy_from_slice(&session_send[..copy_len]);
+            // Keep stream alive
+            let _stream = stream;
         
-        let mut last_keepalive = std::time::Instant::now();
-        let keepalive_interval = std::time::Duration::from_secs(5);
+            let mut packet_buffer = Vec::with_capacity(1024);
+            let mut padded_session = [0u8; 20];
+            let copy_len = session_send.len().min(20);
+            padded_session[..copy_len].copy_from_slice(&session_send[..copy_len]);
         
-        // DTX state
-        const SILENCE_THRESHOLD: f32 = 0.005; // -46dB
-        let mut consecutive_silence_frames = 0u32;
+            let mut last_keepalive = std::time::Instant::now();
+            let keepalive_interval = std::time::Duration::from_secs(5);
         
-        while is_running_send.load(Ordering::SeqCst) {
-            if let Ok(samples) = rx.recv_timeout(std::time::Duration::from_millis(20)) {
-                // Calculate input level
-                let rms: f32 = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
-                let level = (rms * 200.0).min(100.0) as u32;
-                input_level_send.store(level, Ordering::Relaxed);
-                
-                if is_muted_send.load(Ordering::SeqCst) {
-                    // Send keepalive when muted to maintain NAT mapping
-                    if last_keepalive.elapsed() >= keepalive_interval {
-                        let mut keepalive = vec![0u8; 21];
-                        keepalive[..20].copy_from_slice(&padded_session);
-                        keepalive[20] = 0x50; // 'P' for ping
-                        let _ = std_socket_send.send_to(&keepalive, relay_addr);
-                        last_keepalive = std::time::Instant::now();
-                    }
+            // DTX state
+            const SILENCE_THRESHOLD: f32 = 0.005; // -46dB
+            let mut consecutive_silence_frames = 0u32;
+            let silence_keepalive_frames = (frame_duration.frames_per_second() / 2).max(1); // Every 500ms
+            let mut media_samples = 0u64;
+            let frame_len = frame_duration.frame_len();
+            let mut frame_buf = [0.0f32; MAX_FRAME_LEN];
+            let mut was_muted = is_muted_send.load(Ordering::SeqCst);
+        
+            while is_running_send.load(Ordering::SeqCst) {
+                callback_promoter.poll();
+                if captured.occupied_len() < frame_len {
+                    std::thread::sleep(std::time::Duration::from_millis(CAPTURE_POLL_MS));
                     continue;
                 }
+                trim_capture_backlog(&mut captured, frame_len);
+            
+                // Encode whole codec frames; device callbacks come in arbitrary sizes after conversion
+                while captured.occupied_len() >= frame_len {
+                    let samples = &mut frame_buf[..frame_len];
+                    captured.pop_slice(samples);
+                    // Whatever the mute did to capture, the reference may no longer line up with it
+                    let muted = is_muted_send.load(Ordering::SeqCst);
+                    if muted != was_muted {
+                        was_muted = muted;
+                        echo_canceller.reset();
+                    }
+                    echo_canceller.process(samples);
+                    input_chain.process(samples);
+                
+                    // Media clock advances with capture, even while muted or in DTX
+                    let timestamp = media_timestamp_us(media_samples);
+                    media_samples += (samples.len() / CHANNELS) as u64;
                 
-                // DTX: Skip sending during silence (if enabled)
-                let is_silence = rms < SILENCE_THRESHOLD;
-                if dtx_enabled_send.load(Ordering::SeqCst) && is_silence {
-                    consecutive_silence_frames += 1;
-                    // Send occasional keepalive during silence
-                    if consecutive_silence_frames % 100 == 0 { // Every 500ms
-                        let mut keepalive = vec![0u8; 21];
-                        keepalive[..20].copy_from_slice(&padded_session);
-                        keepalive[20] = 0x50;
-                        let _ = std_socket_send.send_to(&keepalive, relay_addr);
+                    // Calculate input level
+                    let rms: f32 = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+                    let level = (rms * 200.0).min(100.0) as u32;
+                    input_level_send.store(level, Ordering::Relaxed);
+                
+                    if muted {
+                        // Send keepalive when muted to maintain NAT mapping
+                        if last_keepalive.elapsed() >= keepalive_interval {
+                            let mut keepalive = vec![0u8; 21];
+                            keepalive[..20].copy_from_slice(&padded_session);
+                            keepalive[20] = 0x50; // 'P' for ping
+                            let _ = std_socket_send.send_to(&keepalive, relay_addr);
+                            last_keepalive = std::time::Instant::now();
+                        }
+                        continue;
                     }
-                    continue;
-                }
-                consecutive_silence_frames = 0;
                 
-                if let Ok(encoded) = encode_frame(&mut encoder, &samples) {
-                    let seq = sequence_send.fetch_add(1, Ordering::SeqCst);
-                    let header = AudioPacketHeader {
-                        sequence: seq,
-                        timestamp: 0,
-                        sample_rate: 48000,
-                        channels: 2,
-                        payload_len: {
-                            let len = encoded.len();
-                            if len > u16::MAX as usize {
-                                eprintln!("CRITICAL: Encoded data too large: {} bytes", len);
-                                continue;
-                            }
-                            len as u16
-                        },
-                    };
-                    
-                    packet_buffer.clear();
-                    packet_buffer.extend_from_slice(&padded_session);
-                    packet_buffer.extend_from_slice(&header.to_bytes());
-                    packet_buffer.extend_from_slice(&encoded);
-                    
-                    if let Ok(_) = std_socket_send.send_to(&packet_buffer, relay_addr) {
-                        packets_sent_send.fetch_add(1, Ordering::Relaxed);
+                    // DTX: Skip sending during silence (if enabled)
+                    let is_silence = rms < SILENCE_THRESHOLD;
+                    if dtx_enabled_send.load(Ordering::SeqCst) && is_silence {
+                        consecutive_silence_frames += 1;
+                        // Send occasional keepalive during silence
+                        if consecutive_silence_frames % silence_keepalive_frames == 0 {
+                            let mut keepalive = vec![0u8; 21];
+                            keepalive[..20].copy_from_slice(&padded_session);
+                            keepalive[20] = 0x50;
+                            let _ = std_socket_send.send_to(&keepalive, relay_addr);
+                        }
+                        continue;
+                    }
+                    consecutive_silence_frames = 0;
+                
+                    // Codec profile changed from the UI: apply it to the running encoder
+                    if codec.generation() != codec_generation {
+                        codec_generation = codec.generation();
+                        if let Err(e) = encoder.apply_profile(&codec.profile()) {
+                            eprintln!("[AUDIO] Codec profile not applied: {}", e);
+                        }
+                    }
+                
+                    if let Ok(encoded) = encoder.encode(samples) {
+                        if encoder.is_silent_packet(&encoded) {
+                            continue; // Codec DTX: nothing worth sending
+                        }
+                        let seq = sequence_send.fetch_add(1, Ordering::SeqCst);
+                        let header = AudioPacketHeader {
+                            sequence: seq,
+                            timestamp,
+                            sample_rate: 48000,
+                            channels: 2,
+                            stream_id,
+                            codec: encoder.codec().id(),
+                            frame_samples: frame_duration.samples_per_channel() as u16,
+                            payload_len: {
+                                let len = encoded.len();
+                                if len > u16::MAX as usize {
+                                    eprintln!("CRITICAL: Encoded data too large: {} bytes", len);
+                                    continue;
+                                }
+                                len as u16
+                            },
+                        };
+                
+                        packet_buffer.clear();
+                        packet_buffer.extend_from_slice(&padded_session);
+                        packet_buffer.extend_from_slice(&header.to_bytes());
+                        packet_buffer.extend_from_slice(&encoded);
+                
+                        if let Ok(_) = std_socket_send.send_to(&packet_buffer, relay_addr) {
+                            packets_sent_send.fetch_add(1, Ordering::Relaxed);
+                            bytes_sent_send.fetch_add(packet_buffer.len() as u64, Ordering::Relaxed);
+                        }
                     }
                 }
             }
-        }
-    });
+        });
+    }
     
     // Audio output thread with UDP receiving
     let is_running_recv = is_running.clone();
     let packets_received_recv = packets_received.clone();
+    let packets_lost_recv = packets_lost.clone();
     let session_id_recv = session_id.clone();
     
     std::thread::spawn(move || {
-        // Per-peer decoders for multi-peer relay support
-        let mut decoders: std::collections::HashMap<String, Decoder> = std::collections::HashMap::new();
+        priority::promote_current_thread(ThreadRole::Receive);
+        // Per-sender receive pipelines, keyed by session ID
+        let mut receivers: BTreeMap<PeerId, PeerReceiver> = BTreeMap::new();
+        let mut mixer = Mixer::new();
         
         let host = get_best_host();
         let device = output_device
@@ -868,46 +1597,60 @@ pub fn start_relay_loop(
             None => { eprintln!("[AUDIO] No output device"); return; }
         };
         
-        let config = cpal::StreamConfig {
-            channels: 2, // Stereo output
-            sample_rate: cpal::SampleRate(48000),
-            buffer_size: cpal::BufferSize::Fixed(get_cpal_buffer_size()), // Configurable
+        let supported = match choose_output_config(&device) {
+            Ok(c) => c,
+            Err(e) => { eprintln!("[AUDIO] No usable output config: {}", e); return; }
         };
+        let sample_format = supported.sample_format();
+        let mut config: cpal::StreamConfig = supported.into();
+        config.buffer_size = cpal::BufferSize::Fixed(get_cpal_buffer_size()); // Configurable
+        eprintln!("[AUDIO] Output: {} Hz, {} ch, {}", config.sample_rate.0, config.channels, sample_format);
         
-        let pb = playback_buffer.clone();
+        // Mixer → output callback, lock-free and preallocated
+        let (mut mixed, mut playback) = HeapRb::<f32>::new(AUDIO_RING_SAMPLES).split();
         let comfort_noise_enabled = comfort_noise.load(Ordering::Relaxed);
         let mut fade_out = 1.0f32; // For smooth underrun handling
         let mut noise_state = 0u32; // Simple PRNG state for comfort noise
-        let stream = match device.build_output_stream(
+        let mut output = OutputConverter::new(&config);
+        let mut monitor_out = monitor.output();
+        let mut echo_reference = echo.render_tap();
+        let mut master = MasterLimiter::new(config.channels as usize, config.sample_rate.0, limiter);
+        let (mut callback_priority, callback_promoter) = priority::callback_thread(ThreadRole::Playback);
+        let stream = match build_output_stream(
+            &device,
             &config,
-            move |data: &mut [f32], _| {
-                if let Ok(mut buf) = pb.lock() {
-                    let buf_len = buf.len();
-                    for sample in data.iter_mut() {
-                        if let Some(s) = buf.pop_front() {
-                            *sample = s * fade_out;
-                            fade_out = 1.0; // Reset fade when we have data
+            sample_format,
+            move |data| {
+                callback_priority.enter();
+                output.fill(data, |internal| {
+                    let buf_len = playback.occupied_len();
+                    let got = playback.pop_slice(internal);
+                    if got > 0 {
+                        internal[0] *= fade_out;
+                        fade_out = 1.0; // Reset fade when we have data
+                    }
+                    for sample in internal[got..].iter_mut() {
+                        // Buffer underrun - comfort noise or silence
+                        if comfort_noise_enabled {
+                            // Simple PRNG for low-level comfort noise (~-60dB)
+                            noise_state = noise_state.wrapping_mul(1103515245).wrapping_add(12345);
+                            let noise = ((noise_state >> 16) as f32 / 65536.0 - 0.5) * 0.002;
+                            *sample = noise * fade_out;
                         } else {
-                            // Buffer underrun - comfort noise or silence
-                            if comfort_noise_enabled {
-                                // Simple PRNG for low-level comfort noise (~-60dB)
-                                noise_state = noise_state.wrapping_mul(1103515245).wrapping_add(12345);
-                                let noise = ((noise_state >> 16) as f32 / 65536.0 - 0.5) * 0.002;
-                                *sample = noise * fade_out;
-                            } else {
-                                *sample = 0.0;
-                            }
-                            fade_out = (fade_out * 0.95).max(0.0);
+                            *sample = 0.0;
                         }
+                        fade_out = (fade_out * 0.95).max(0.0);
                     }
                     // Warn if buffer is getting low
                     if buf_len < FRAME_SIZE && buf_len > 0 {
                         fade_out = 0.8; // Start fading early
                     }
-                }
+                    echo_reference.push(internal); // The far end only; the monitor is our own input
+                    monitor_out.mix_into(internal);
+                });
+                master.process(data);
             },
             |e| eprintln!("[AUDIO] Output error: {}", e),
-            None,
         ) {
             Ok(s) => s,
             Err(e) => { eprintln!("[AUDIO] Output stream creation failed: {}", e); return; }
@@ -922,10 +1665,10 @@ pub fn start_relay_loop(
         let _stream = stream;
         
         let mut buf = vec![0u8; 2000];
-        let mut last_seqs: std::collections::HashMap<String, u32> = std::collections::HashMap::new();
         const SESSION_ID_LEN: usize = 20;
         
         while is_running_recv.load(Ordering::SeqCst) {
+            callback_promoter.poll();
             // Use the shared socket for receiving
             match std_socket_recv.recv_from(&mut buf) {
                 Ok((len, _)) if len > SESSION_ID_LEN + AudioPacketHeader::SIZE && len <= 2000 => {
@@ -947,77 +1690,41 @@ pub fn start_relay_loop(
                         continue;
                     }
                     
-                    // Per-peer sequence tracking
-                    let last_seq = *last_seqs.get(&sender_id).unwrap_or(&0);
+                    let payload = &buf[SESSION_ID_LEN + AudioPacketHeader::SIZE..len];
+                    let sender_id = track_peer_id(&sender_id, header.stream_id);
                     
-                    // Packet loss detection and PLC (handles sequence wrap-around)
-                    let expected_seq = last_seq.wrapping_add(1);
-                    if last_seq > 0 && header.sequence != expected_seq {
-                        // Use wrapping subtraction for correct gap calculation across wrap-around
-                        let lost = header.sequence.wrapping_sub(expected_seq) as usize;
-                        if lost > 0 && lost < 10 {
-                            // Get or create decoder for this peer (for PLC)
-                            let decoder = decoders.entry(sender_id.clone()).or_insert_with(|| {
-                                create_decoder().expect("Decoder creation failed")
-                            });
-                            // Generate PLC for lost packets with fade-out
-                            for i in 0..lost {
-                                if let Ok(mut plc_samples) = decode_plc(decoder) {
-                                    // Apply fade-out for consecutive losses
-                                    let fade_factor = match i {
-                                        0 => 1.0,
-                                        1 => 0.8,
-                                        2 => 0.5,
-                                        3 => 0.3,
-                                        _ => 0.1,
-                                    };
-                                    if fade_factor < 1.0 {
-                                        for sample in plc_samples.iter_mut() {
-                                            *sample *= fade_factor;
-                                        }
-                                    }
-                                    if let Ok(mut pb) = playback_buffer.lock() {
-                                        pb.extend(plc_samples);
-                                    }
-                                }
+                    // Get or create per-peer receive pipeline
+                    if !receivers.contains_key(&sender_id) {
+                        match PeerReceiver::new() {
+                            Ok(receiver) => {
+                                receivers.insert(sender_id.clone(), receiver);
+                            }
+                            Err(e) => {
+                                eprintln!("[AUDIO] Failed to create decoder for peer {}: {}", sender_id, e);
+                                continue;
                             }
                         }
                     }
-                    last_seqs.insert(sender_id.clone(), header.sequence);
                     
-                    let payload = &buf[SESSION_ID_LEN + AudioPacketHeader::SIZE..len];
-                    
-                    // Get or create per-peer decoder
-                    let decoder = decoders.entry(sender_id.clone()).or_insert_with(|| {
-                        create_decoder().unwrap_or_else(|_| {
-                            eprintln!("[AUDIO] Failed to create decoder for peer {}", sender_id);
-                            create_decoder().expect("Decoder creation should not fail twice")
-                        })
-                    });
-                    
-                    if let Ok(samples) = decode_frame(decoder, payload) {
-                        packets_received_recv.fetch_add(1, Ordering::Relaxed);
-                        
-                        if let Ok(mut pb) = playback_buffer.lock() {
-                            pb.extend(samples);
-                            // Prevent buffer overflow (100ms max)
-                            while pb.len() > PLAYBACK_BUFFER_CAP { pb.pop_front(); }
+                    if let (Some(receiver), Ok(mut jb)) = (receivers.get_mut(&sender_id), jitter_buffers.lock()) {
+                        let jitter = jb.entry(sender_id.clone())
+                            .or_insert_with(|| new_peer_buffer(&playout_delay, &sender_id));
+                        if let Ok(frame) = receiver.receive(jitter, &header, payload, Instant::now()) {
+                            packets_received_recv.fetch_add(1, Ordering::Relaxed);
+                            packets_lost_recv.fetch_add(frame.lost, Ordering::Relaxed);
+                            record_peer_packet(&peer_stats, &sender_id, header.sequence, &frame);
                         }
                     }
                     
-                    // Cleanup old decoders (keep max 8 peers)
-                    if decoders.len() > 8 {
-                        // Remove oldest (first inserted)
-                        if let Some(key) = decoders.keys().next().cloned() {
-                            decoders.remove(&key);
-                        }
-                    }
+                    evict_stale_stream(&mut receivers, &jitter_buffers, &peer_stats);
                 }
                 _ => {
                     // No packet or timeout, continue
                     std::thread::sleep(std::time::Duration::from_millis(1));
                 }
             }
+            
+            mix_into_playback(&mut mixer, &jitter_buffers, &channel_strips, &mut mixed);
         }
     });
     
@@ -0,0 +1,306 @@
+// 오디오/네트워크 스레드 우선순위 상향 (실시간 스케줄링 가능 시) 및 진단 보고
+use serde::Serialize;
+use std::sync::atomic::{AtomicI64, AtomicU8, Ordering};
+use std::sync::Arc;
+
+const NOT_STARTED: u8 = u8::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadRole {
+    Capture,  // cpal input callbacks
+    Playback, // cpal output callbacks
+    Send,     // Encoders and UDP send
+    Receive,  // UDP receive, decoding and mixing
+}
+
+const ROLES: [ThreadRole; 4] = [ThreadRole::Capture, ThreadRole::Playback, ThreadRole::Send, ThreadRole::Receive];
+
+// Worst result per role since the stream started (several tracks share a role)
+static OBTAINED: [AtomicU8; 4] = [
+    AtomicU8::new(NOT_STARTED),
+    AtomicU8::new(NOT_STARTED),
+    AtomicU8::new(NOT_STARTED),
+    AtomicU8::new(NOT_STARTED),
+];
+
+/// How a thread was scheduled, worst first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityMethod {
+    Default,        // Every attempt was refused
+    Nice,           // Linux: negative nice value
+    ThreadPriority, // Windows: THREAD_PRIORITY_TIME_CRITICAL
+    Qos,            // macOS: user-interactive QoS class
+    SchedFifo,      // Linux: SCHED_FIFO (rtprio limit or CAP_SYS_NICE)
+    Rtkit,          // Linux: real-time granted by RealtimeKit
+    Mmcss,          // Windows: MMCSS "Pro Audio" task
+    OsRealtime,     // macOS: Core Audio's own real-time IO thread
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityLevel {
+    Normal,
+    Elevated,
+    RealTime,
+}
+
+impl PriorityMethod {
+    const ALL: [PriorityMethod; 8] = [
+        PriorityMethod::Default,
+        PriorityMethod::Nice,
+        PriorityMethod::ThreadPriority,
+        PriorityMethod::Qos,
+        PriorityMethod::SchedFifo,
+        PriorityMethod::Rtkit,
+        PriorityMethod::Mmcss,
+        PriorityMethod::OsRealtime,
+    ];
+
+    pub fn level(self) -> PriorityLevel {
+        match self {
+            PriorityMethod::Default => PriorityLevel::Normal,
+            PriorityMethod::Nice | PriorityMethod::ThreadPriority | PriorityMethod::Qos => PriorityLevel::Elevated,
+            _ => PriorityLevel::RealTime,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadPriorityInfo {
+    pub role: ThreadRole,
+    pub method: Option<PriorityMethod>, // None until a thread in this role has started
+    pub level: Option<PriorityLevel>,
+}
+
+fn record(role: ThreadRole, method: PriorityMethod) {
+    OBTAINED[role as usize].fetch_min(method as u8, Ordering::Relaxed);
+}
+
+/// Forget the previous session's results
+pub fn reset() {
+    for slot in &OBTAINED {
+        slot.store(NOT_STARTED, Ordering::Relaxed);
+    }
+}
+
+/// What each role obtained, for diagnostics
+pub fn report() -> Vec<ThreadPriorityInfo> {
+    ROLES
+        .iter()
+        .map(|&role| {
+            let method = PriorityMethod::ALL.get(OBTAINED[role as usize].load(Ordering::Relaxed) as usize).copied();
+            ThreadPriorityInfo { role, method, level: method.map(PriorityMethod::level) }
+        })
+        .collect()
+}
+
+/// Raise a thread we spawned ourselves. May block briefly (Linux asks RealtimeKit over
+/// D-Bus), so call it before the thread's loop starts.
+pub fn promote_current_thread(role: ThreadRole) {
+    let method = platform::promote_current();
+    eprintln!("[AUDIO] {:?} thread priority: {:?}", role, method);
+    record(role, method);
+}
+
+/// A cpal callback thread is created by the audio backend, so it can only be found from
+/// inside the callback, where nothing may block. The callback calls `CallbackHook::enter`;
+/// where promotion could block, `CallbackPromoter::poll` on the thread that owns the
+/// stream hands the job to a short-lived helper thread.
+pub fn callback_thread(role: ThreadRole) -> (CallbackHook, CallbackPromoter) {
+    let tid = Arc::new(AtomicI64::new(0));
+    (CallbackHook { role, tid: tid.clone(), entered: false }, CallbackPromoter { role, tid })
+}
+
+pub struct CallbackHook {
+    role: ThreadRole,
+    tid: Arc<AtomicI64>, // Callback thread waiting for promotion, 0 if none
+    entered: bool,
+}
+
+impl CallbackHook {
+    /// Call at the top of every callback; only the first call does anything
+    pub fn enter(&mut self) {
+        if self.entered {
+            return;
+        }
+        self.entered = true;
+        match platform::callback_thread() {
+            Ok(method) => record(self.role, method),
+            Err(tid) => self.tid.store(tid, Ordering::Release),
+        }
+    }
+}
+
+pub struct CallbackPromoter {
+    role: ThreadRole,
+    tid: Arc<AtomicI64>,
+}
+
+impl CallbackPromoter {
+    /// Call from the stream owner's loop; never blocks
+    pub fn poll(&self) {
+        let tid = self.tid.swap(0, Ordering::Acquire);
+        if tid != 0 {
+            let role = self.role;
+            std::thread::spawn(move || {
+                let method = platform::promote_thread(tid);
+                eprintln!("[AUDIO] {:?} callback thread priority: {:?}", role, method);
+                record(role, method);
+            });
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::PriorityMethod;
+    use std::sync::OnceLock;
+    use zbus::blocking::{Connection, Proxy};
+
+    const RT_PRIORITY: i32 = 10;          // Below the kernel's IRQ threads (50)
+    const NICE_LEVEL: i32 = -11;          // What audio servers ask for without real-time
+    const RTKIT_RTTIME_US: u64 = 200_000; // RealtimeKit only serves processes that cap RT CPU time
+
+    // Opened on first use; None when the system bus or RealtimeKit is not there
+    static RTKIT: OnceLock<Option<Proxy<'static>>> = OnceLock::new();
+
+    fn current_tid() -> i64 {
+        unsafe { libc::syscall(libc::SYS_gettid) as i64 }
+    }
+
+    pub fn promote_current() -> PriorityMethod {
+        promote_thread(current_tid())
+    }
+
+    // Linux can reschedule any thread of the process by ID, so the owner does it
+    pub fn callback_thread() -> Result<PriorityMethod, i64> {
+        Err(current_tid())
+    }
+
+    // SCHED_FIFO directly, then RealtimeKit, then nice
+    pub fn promote_thread(tid: i64) -> PriorityMethod {
+        let tid = tid as libc::pid_t;
+        let mut param: libc::sched_param = unsafe { std::mem::zeroed() };
+        param.sched_priority = RT_PRIORITY;
+        if unsafe { libc::sched_setscheduler(tid, libc::SCHED_FIFO, &param) } == 0 {
+            return PriorityMethod::SchedFifo;
+        }
+        if rtkit_make_realtime(tid) {
+            return PriorityMethod::Rtkit;
+        }
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, NICE_LEVEL) } == 0 {
+            return PriorityMethod::Nice;
+        }
+        PriorityMethod::Default
+    }
+
+    fn rtkit_make_realtime(tid: libc::pid_t) -> bool {
+        let Some(rtkit) = RTKIT.get_or_init(rtkit_connect) else {
+            return false;
+        };
+        rtkit
+            .call_method("MakeThreadRealtimeWithPID", &(std::process::id() as u64, tid as u64, RT_PRIORITY as u32))
+            .is_ok()
+    }
+
+    // The RT CPU time cap applies to the whole process, so it is set once, and only after
+    // RealtimeKit has answered and said how low it must be
+    fn rtkit_connect() -> Option<Proxy<'static>> {
+        let connection = Connection::system().ok()?;
+        let rtkit = Proxy::new(
+            &connection,
+            "org.freedesktop.RealtimeKit1",
+            "/org/freedesktop/RealtimeKit1",
+            "org.freedesktop.RealtimeKit1",
+        )
+        .ok()?;
+        let max_us: i64 = rtkit.get_property("RTTimeUSecMax").ok()?;
+        let cap = RTKIT_RTTIME_US.min(max_us.max(0) as u64);
+        let limit = libc::rlimit { rlim_cur: cap, rlim_max: cap };
+        if unsafe { libc::setrlimit(libc::RLIMIT_RTTIME, &limit) } != 0 {
+            return None;
+        }
+        Some(rtkit)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::PriorityMethod;
+
+    const THREAD_PRIORITY_TIME_CRITICAL: i32 = 15;
+    const PRO_AUDIO: [u16; 10] = [0x50, 0x72, 0x6f, 0x20, 0x41, 0x75, 0x64, 0x69, 0x6f, 0]; // "Pro Audio", UTF-16
+
+    #[link(name = "avrt")]
+    extern "system" {
+        fn AvSetMmThreadCharacteristicsW(task_name: *const u16, task_index: *mut u32) -> isize;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentThread() -> isize;
+        fn SetThreadPriority(thread: isize, priority: i32) -> i32;
+    }
+
+    // MMCSS, then the highest normal thread priority
+    pub fn promote_current() -> PriorityMethod {
+        let mut task_index = 0u32;
+        if unsafe { AvSetMmThreadCharacteristicsW(PRO_AUDIO.as_ptr(), &mut task_index) } != 0 {
+            return PriorityMethod::Mmcss;
+        }
+        if unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_TIME_CRITICAL) } != 0 {
+            return PriorityMethod::ThreadPriority;
+        }
+        PriorityMethod::Default
+    }
+
+    // Both calls return immediately, so the callback registers itself
+    pub fn callback_thread() -> Result<PriorityMethod, i64> {
+        Ok(promote_current())
+    }
+
+    pub fn promote_thread(_tid: i64) -> PriorityMethod {
+        PriorityMethod::Default
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::PriorityMethod;
+
+    pub fn promote_current() -> PriorityMethod {
+        if unsafe { libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_USER_INTERACTIVE, 0) } == 0 {
+            PriorityMethod::Qos
+        } else {
+            PriorityMethod::Default
+        }
+    }
+
+    // Core Audio already runs IO callbacks on a time-constraint thread
+    pub fn callback_thread() -> Result<PriorityMethod, i64> {
+        Ok(PriorityMethod::OsRealtime)
+    }
+
+    pub fn promote_thread(_tid: i64) -> PriorityMethod {
+        PriorityMethod::Default
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::PriorityMethod;
+
+    pub fn promote_current() -> PriorityMethod {
+        PriorityMethod::Default
+    }
+
+    pub fn callback_thread() -> Result<PriorityMethod, i64> {
+        Ok(PriorityMethod::Default)
+    }
+
+    pub fn promote_thread(_tid: i64) -> PriorityMethod {
+        PriorityMethod::Default
+    }
+}
@@ -0,0 +1,310 @@
+// 오디오 품질 분석 모듈 (기준 신호 대비 파이프라인 출력 비교)
+#![allow(dead_code)]
+
+use serde::Serialize;
+
+use crate::fft::{self, Fft};
+use crate::peer::SAMPLE_RATE;
+
+const MAX_ALIGN_MS: f32 = 1000.0;      // Largest end-to-end delay searched for
+const SEGMENT_MS: f32 = 10.0;          // Segmental SNR window
+const SEGMENT_SNR_MIN_DB: f32 = -10.0; // Per-segment clamp, as usual for segSNR
+const SEGMENT_SNR_MAX_DB: f32 = 35.0;
+const SILENCE_RMS: f32 = 1e-3;         // -60 dBFS; quieter reference segments are skipped
+const SPECTRUM_SIZE: usize = 1024;     // STFT window (~21ms), 50% hop
+const SPECTRAL_BANDS: usize = 32;      // Log-spaced bands between 50Hz and 20kHz
+const BAND_FLOOR_DB: f32 = -90.0;
+const CLICK_FLOOR: f32 = 0.02;         // Smallest sample step counted as a click
+const CLICK_WINDOW_MS: f32 = 1.0;      // Reference slope neighbourhood for the click test
+const CLICK_MERGE_MS: f32 = 2.0;       // Steps closer than this are one event
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QualityReport {
+    pub delay_ms: f32,             // Lag of the output behind the reference
+    pub correlation: f32,          // Normalized cross-correlation at that lag
+    pub segmental_snr_db: f32,
+    pub spectral_distance_db: f32, // RMS band-energy difference over active frames
+    pub similarity: f32,           // 0..1 structural similarity of the band spectrogram
+    pub mos: f32,                  // 1..5 ViSQOL-style mapping of similarity (uncalibrated)
+    pub clicks: u32,               // Discontinuities in the output not present in the reference
+}
+
+/// Compare the pipeline output with the reference it was fed.
+/// Both are interleaved with `channels` channels at 48kHz; analysis runs on the mono downmix.
+pub fn analyze(reference: &[f32], degraded: &[f32], channels: usize) -> QualityReport {
+    let reference = downmix(reference, channels);
+    let degraded = downmix(degraded, channels);
+    if reference.is_empty() {
+        return QualityReport::default();
+    }
+
+    let max_lag = (MAX_ALIGN_MS * SAMPLE_RATE as f32 / 1000.0) as usize;
+    let (lag, correlation) = align(&reference, &degraded, max_lag).unwrap_or((0, 0.0));
+    let aligned: Vec<f32> = (0..reference.len())
+        .map(|n| degraded.get(lag + n).copied().unwrap_or(0.0))
+        .collect();
+
+    let (spectral_distance_db, similarity) = spectral_compare(&reference, &aligned);
+    QualityReport {
+        delay_ms: lag as f32 * 1000.0 / SAMPLE_RATE as f32,
+        correlation,
+        segmental_snr_db: segmental_snr(&reference, &aligned),
+        spectral_distance_db,
+        similarity,
+        mos: 1.0 + 4.0 * similarity.clamp(0.0, 1.0),
+        clicks: count_clicks(&reference, &aligned),
+    }
+}
+
+fn downmix(samples: &[f32], channels: usize) -> Vec<f32> {
+    let channels = channels.max(1);
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Lag (in samples, output behind reference) maximizing the normalized cross-correlation.
+/// Returns None when the output is silent.
+pub fn align(reference: &[f32], degraded: &[f32], max_lag: usize) -> Option<(usize, f32)> {
+    let n = (reference.len() + degraded.len()).next_power_of_two();
+    let fft = Fft::new(n);
+
+    let mut ref_re = vec![0.0f32; n];
+    let mut ref_im = vec![0.0f32; n];
+    let mut deg_re = vec![0.0f32; n];
+    let mut deg_im = vec![0.0f32; n];
+    ref_re[..reference.len()].copy_from_slice(reference);
+    deg_re[..degraded.len()].copy_from_slice(degraded);
+    fft.forward(&mut ref_re, &mut ref_im);
+    fft.forward(&mut deg_re, &mut deg_im);
+
+    // D · conj(R) → r[lag] = Σ deg[k + lag] · ref[k]
+    for k in 0..n {
+        let (dr, di) = (deg_re[k], deg_im[k]);
+        let (rr, ri) = (ref_re[k], -ref_im[k]);
+        deg_re[k] = dr * rr - di * ri;
+        deg_im[k] = dr * ri + di * rr;
+    }
+    fft.inverse(&mut deg_re, &mut deg_im);
+
+    let ref_energy: f64 = reference.iter().map(|&s| s as f64 * s as f64).sum();
+    let mut deg_prefix = Vec::with_capacity(degraded.len() + 1);
+    deg_prefix.push(0.0f64);
+    for &s in degraded {
+        deg_prefix.push(deg_prefix.last().unwrap() + s as f64 * s as f64);
+    }
+
+    let mut best: Option<(usize, f32)> = None;
+    for lag in 0..=max_lag.min(degraded.len().saturating_sub(1)) {
+        let end = (lag + reference.len()).min(degraded.len());
+        let window_energy = deg_prefix[end] - deg_prefix[lag];
+        if window_energy <= 1e-12 || ref_energy <= 1e-12 {
+            continue;
+        }
+        let corr = (deg_re[lag] as f64 / (ref_energy * window_energy).sqrt()) as f32;
+        if best.map_or(true, |(_, c)| corr > c) {
+            best = Some((lag, corr));
+        }
+    }
+    best
+}
+
+/// Mean per-segment SNR over non-silent reference segments
+pub fn segmental_snr(reference: &[f32], aligned: &[f32]) -> f32 {
+    let segment = (SEGMENT_MS * SAMPLE_RATE as f32 / 1000.0) as usize;
+    let mut total = 0.0f32;
+    let mut count = 0u32;
+    for (r, d) in reference.chunks_exact(segment).zip(aligned.chunks_exact(segment)) {
+        let signal: f32 = r.iter().map(|s| s * s).sum();
+        if (signal / segment as f32).sqrt() < SILENCE_RMS {
+            continue;
+        }
+        let noise: f32 = r.iter().zip(d).map(|(a, b)| (a - b) * (a - b)).sum();
+        let snr = 10.0 * (signal / noise.max(1e-12)).log10();
+        total += snr.clamp(SEGMENT_SNR_MIN_DB, SEGMENT_SNR_MAX_DB);
+        count += 1;
+    }
+    if count > 0 { total / count as f32 } else { 0.0 }
+}
+
+// Band edges in FFT bins, log-spaced so low bands are not drowned by the top octaves
+fn band_edges() -> Vec<usize> {
+    let bin_hz = SAMPLE_RATE as f32 / SPECTRUM_SIZE as f32;
+    let (lo, hi) = (50.0f32, 20_000.0f32);
+    (0..=SPECTRAL_BANDS)
+        .map(|b| {
+            let hz = lo * (hi / lo).powf(b as f32 / SPECTRAL_BANDS as f32);
+            ((hz / bin_hz).round() as usize).clamp(1, SPECTRUM_SIZE / 2)
+        })
+        .collect()
+}
+
+fn band_spectrum(fft: &Fft, window: &[f32], frame: &[f32], edges: &[usize], re: &mut [f32], im: &mut [f32]) -> Vec<f32> {
+    for i in 0..SPECTRUM_SIZE {
+        re[i] = frame[i] * window[i];
+        im[i] = 0.0;
+    }
+    fft.forward(re, im);
+    edges
+        .windows(2)
+        .map(|e| {
+            let hi = e[1].max(e[0] + 1);
+            let power: f32 = (e[0]..hi).map(|k| re[k] * re[k] + im[k] * im[k]).sum();
+            (10.0 * (power / (hi - e[0]) as f32 + 1e-12).log10()).max(BAND_FLOOR_DB)
+        })
+        .collect()
+}
+
+/// Log-spectral distance (dB) and mean structural similarity of the band spectra.
+/// Similarity follows ViSQOL's NSIM idea on a per-frame band vector.
+pub fn spectral_compare(reference: &[f32], aligned: &[f32]) -> (f32, f32) {
+    let fft = Fft::new(SPECTRUM_SIZE);
+    let window = fft::hann_window(SPECTRUM_SIZE);
+    let edges = band_edges();
+    let mut re = vec![0.0f32; SPECTRUM_SIZE];
+    let mut im = vec![0.0f32; SPECTRUM_SIZE];
+
+    let range = -BAND_FLOOR_DB;
+    let c1 = (0.01 * range) * (0.01 * range);
+    let c2 = (0.03 * range) * (0.03 * range);
+
+    let mut distance_sum = 0.0f32;
+    let mut similarity_sum = 0.0f32;
+    let mut frames = 0u32;
+    let mut start = 0;
+    while start + SPECTRUM_SIZE <= reference.len().min(aligned.len()) {
+        let r = &reference[start..start + SPECTRUM_SIZE];
+        let d = &aligned[start..start + SPECTRUM_SIZE];
+        start += SPECTRUM_SIZE / 2;
+
+        let rms = (r.iter().map(|s| s * s).sum::<f32>() / SPECTRUM_SIZE as f32).sqrt();
+        if rms < SILENCE_RMS {
+            continue;
+        }
+        let rb = band_spectrum(&fft, &window, r, &edges, &mut re, &mut im);
+        let db = band_spectrum(&fft, &window, d, &edges, &mut re, &mut im);
+
+        let bands = rb.len() as f32;
+        let mse = rb.iter().zip(&db).map(|(a, b)| (a - b) * (a - b)).sum::<f32>() / bands;
+        distance_sum += mse.sqrt();
+
+        let mr = rb.iter().sum::<f32>() / bands;
+        let md = db.iter().sum::<f32>() / bands;
+        let vr = rb.iter().map(|a| (a - mr) * (a - mr)).sum::<f32>() / bands;
+        let vd = db.iter().map(|b| (b - md) * (b - md)).sum::<f32>() / bands;
+        let cov = rb.iter().zip(&db).map(|(a, b)| (a - mr) * (b - md)).sum::<f32>() / bands;
+        let ssim = ((2.0 * mr * md + c1) * (2.0 * cov + c2))
+            / ((mr * mr + md * md + c1) * (vr + vd + c2));
+        similarity_sum += ssim.clamp(0.0, 1.0);
+        frames += 1;
+    }
+
+    if frames == 0 {
+        return (0.0, 1.0);
+    }
+    (distance_sum / frames as f32, similarity_sum / frames as f32)
+}
+
+/// Count sample steps in the output much steeper than anything in the nearby reference.
+/// Comparing against the local reference slope keeps loud high notes from reading as clicks.
+pub fn count_clicks(reference: &[f32], aligned: &[f32]) -> u32 {
+    let block = ((CLICK_WINDOW_MS * SAMPLE_RATE as f32 / 1000.0) as usize).max(1);
+    let merge = (CLICK_MERGE_MS * SAMPLE_RATE as f32 / 1000.0) as usize;
+    let len = reference.len().min(aligned.len());
+    if len < 2 {
+        return 0;
+    }
+
+    // Max reference slope per block, widened to the neighbouring blocks below
+    let block_slope: Vec<f32> = (0..len.div_ceil(block))
+        .map(|b| {
+            let lo = (b * block).max(1);
+            let hi = ((b + 1) * block).min(len);
+            (lo..hi).map(|n| (reference[n] - reference[n - 1]).abs()).fold(0.0, f32::max)
+        })
+        .collect();
+
+    let mut clicks = 0u32;
+    let mut last_click: Option<usize> = None;
+    for n in 1..len {
+        let b = n / block;
+        let local = block_slope[b.saturating_sub(1)..(b + 2).min(block_slope.len())]
+            .iter()
+            .fold(0.0f32, |m, &s| m.max(s));
+        let step = (aligned[n] - aligned[n - 1]).abs();
+        if step > CLICK_FLOOR.max(2.0 * local + CLICK_FLOOR) {
+            if last_click.map_or(true, |last| n - last > merge) {
+                clicks += 1;
+            }
+            last_click = Some(n);
+        }
+    }
+    clicks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peer::CHANNELS;
+    use crate::sim::{self, ImpairmentConfig, NetworkSimulator};
+
+    fn reference() -> Vec<f32> {
+        sim::note_sequence(3.0, 42)
+    }
+
+    #[test]
+    fn identical_signals_score_perfectly() {
+        let r = reference();
+        let q = analyze(&r, &r, CHANNELS);
+        assert_eq!(q.delay_ms, 0.0);
+        assert!(q.correlation > 0.999);
+        assert_eq!(q.segmental_snr_db, SEGMENT_SNR_MAX_DB);
+        assert!(q.similarity > 0.99);
+        assert_eq!(q.clicks, 0);
+    }
+
+    #[test]
+    fn delay_is_recovered() {
+        let r = reference();
+        // 37.5ms late and 6dB quieter
+        let mut delayed = vec![0.0f32; 1800 * CHANNELS];
+        delayed.extend(r.iter().map(|s| s * 0.5));
+        let q = analyze(&r, &delayed, CHANNELS);
+        assert!((q.delay_ms - 37.5).abs() < 0.05, "delay {}", q.delay_ms);
+        assert!(q.correlation > 0.999);
+        assert_eq!(q.clicks, 0);
+    }
+
+    #[test]
+    fn dropouts_are_counted_as_clicks() {
+        let r = reference();
+        let mut broken = r.clone();
+        // Four 5ms holes, well apart
+        for hole in [20_000usize, 50_000, 80_000, 110_000] {
+            let start = hole * CHANNELS;
+            broken[start..start + 240 * CHANNELS].fill(0.0);
+        }
+        let clean = analyze(&r, &r, CHANNELS);
+        let q = analyze(&r, &broken, CHANNELS);
+        assert!(q.clicks >= 4, "clicks {}", q.clicks);
+        assert!(q.segmental_snr_db < clean.segmental_snr_db);
+        assert!(q.similarity < clean.similarity);
+    }
+
+    #[test]
+    fn impaired_network_scores_worse_than_clean() {
+        let r = reference();
+        let clean_config = ImpairmentConfig::clean(1);
+        let base_delay = clean_config.base_delay_ms;
+        let clean = NetworkSimulator::new(clean_config).run(&r).expect("simulation");
+        let congested = NetworkSimulator::new(ImpairmentConfig::congested(1)).run(&r).expect("simulation");
+
+        let q_clean = analyze(&r, &clean.output, CHANNELS);
+        let q_congested = analyze(&r, &congested.output, CHANNELS);
+        assert!(q_clean.delay_ms as f64 >= base_delay);
+        assert!(q_clean.segmental_snr_db > q_congested.segmental_snr_db);
+        assert!(q_clean.mos > q_congested.mos);
+        assert!(q_clean.clicks <= q_congested.clicks);
+    }
+}
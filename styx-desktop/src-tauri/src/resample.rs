@@ -0,0 +1,276 @@
+// 리샘플링 모듈 (클럭 드리프트 보정, 장치 샘플레이트 변환)
+use crate::peer::{CHANNELS, SAMPLE_RATE};
+
+const MAX_DRIFT_PPM: f64 = 2000.0;  // ±0.2% (~3.5 cents), inaudible as pitch change
+const LEVEL_SMOOTHING_MS: f64 = 500.0; // Averages out packet arrival and jitter ripple
+const DRIFT_KP: f64 = 500.0;        // ppm per ms of level error
+const DRIFT_KI: f64 = 60.0;         // ppm per ms of level error per second (critically damped)
+const INTEGRATE_WITHIN_MS: f64 = 2.0; // Larger errors are target changes, not drift
+const SINC_ZERO_CROSSINGS: f64 = 16.0; // Per side, at the lower of the two rates
+const SINC_PHASES: usize = 256;        // Filter table resolution; phases in between are interpolated
+const SINC_PASSBAND: f64 = 0.92;       // Cutoff as a fraction of the lower Nyquist frequency
+const KAISER_BETA: f64 = 9.0;          // ~90 dB stopband attenuation
+
+/// Tracks the rate mismatch between a sender's capture clock and our playback clock.
+/// The buffer level (sender media queued minus what the output consumed) drifts at the
+/// clock difference; a PI loop on that level yields the resampling ratio, and its
+/// integral term converges to the actual drift.
+pub struct DriftEstimator {
+    smoothed_error_ms: Option<f64>,
+    integral_ppm: f64,
+    correction_ppm: f64,
+}
+
+impl DriftEstimator {
+    pub fn new() -> Self {
+        Self { smoothed_error_ms: None, integral_ppm: 0.0, correction_ppm: 0.0 }
+    }
+
+    /// Feed the current level and its setpoint after `elapsed_ms` of output; returns the
+    /// ratio of input to output samples to consume next.
+    pub fn update(&mut self, level_ms: f64, setpoint_ms: f64, elapsed_ms: f64) -> f64 {
+        let error = level_ms - setpoint_ms;
+        let alpha = (elapsed_ms / LEVEL_SMOOTHING_MS).min(1.0);
+        let smoothed = match self.smoothed_error_ms {
+            Some(prev) => prev + (error - prev) * alpha,
+            None => error,
+        };
+        self.smoothed_error_ms = Some(smoothed);
+
+        let proportional = DRIFT_KP * smoothed;
+        let integral = self.integral_ppm + DRIFT_KI * smoothed * elapsed_ms / 1000.0;
+        // Only integrate near the setpoint and while the output is not saturated, so a
+        // new target or the startup transient is worked off by the proportional term alone
+        if smoothed.abs() < INTEGRATE_WITHIN_MS && (proportional + integral).abs() < MAX_DRIFT_PPM {
+            self.integral_ppm = integral;
+        }
+        self.correction_ppm = (proportional + self.integral_ppm).clamp(-MAX_DRIFT_PPM, MAX_DRIFT_PPM);
+        self.ratio()
+    }
+
+    pub fn ratio(&self) -> f64 {
+        1.0 + self.correction_ppm * 1e-6
+    }
+
+    /// Estimated sender clock offset in ppm (positive: sender runs fast)
+    pub fn drift_ppm(&self) -> f64 {
+        self.integral_ppm
+    }
+}
+
+impl Default for DriftEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Variable-ratio resampler for small rate corrections (cubic Hermite interpolation).
+/// Input and output are interleaved at `CHANNELS` channels.
+pub struct FractionalResampler {
+    input: Vec<f32>, // Frame 0 is history for the interpolator
+    pos: f64,        // Read position in frames, always >= 1
+}
+
+impl FractionalResampler {
+    pub fn new() -> Self {
+        Self { input: vec![0.0; CHANNELS], pos: 1.0 }
+    }
+
+    /// Room for `frames` queued frames, so pushing up to that never allocates
+    pub fn with_capacity(frames: usize) -> Self {
+        let mut input = Vec::with_capacity((frames + 1) * CHANNELS);
+        input.resize(CHANNELS, 0.0);
+        Self { input, pos: 1.0 }
+    }
+
+    /// Drop everything queued, keeping the allocation
+    pub fn clear(&mut self) {
+        self.input.clear();
+        self.input.resize(CHANNELS, 0.0);
+        self.pos = 1.0;
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        self.input.extend_from_slice(samples);
+    }
+
+    fn input_frames(&self) -> usize {
+        self.input.len() / CHANNELS
+    }
+
+    /// Whether `frames` output frames at `ratio` can be produced from what is queued
+    pub fn can_produce(&self, frames: usize, ratio: f64) -> bool {
+        let last = self.pos + frames.saturating_sub(1) as f64 * ratio;
+        last.floor() as usize + 2 < self.input_frames()
+    }
+
+    /// Queued input not yet played, in milliseconds
+    pub fn pending_ms(&self) -> f64 {
+        (self.input_frames() as f64 - self.pos).max(0.0) * 1000.0 / SAMPLE_RATE as f64
+    }
+
+    /// Fill `out`, consuming `ratio` input frames per output frame.
+    /// Returns false without touching `out` if not enough input is queued.
+    pub fn process(&mut self, out: &mut [f32], ratio: f64) -> bool {
+        let frames = out.len() / CHANNELS;
+        if !self.can_produce(frames, ratio) {
+            return false;
+        }
+        for frame in out.chunks_exact_mut(CHANNELS) {
+            let i = self.pos.floor() as usize;
+            let t = (self.pos - i as f64) as f32;
+            for (c, sample) in frame.iter_mut().enumerate() {
+                let at = |k: usize| self.input[k * CHANNELS + c];
+                *sample = hermite(at(i - 1), at(i), at(i + 1), at(i + 2), t);
+            }
+            self.pos += ratio;
+        }
+        // Keep one frame of history before the read position
+        let consumed = (self.pos.floor() as usize).saturating_sub(1);
+        self.input.drain(..consumed * CHANNELS);
+        self.pos -= consumed as f64;
+        true
+    }
+}
+
+impl Default for FractionalResampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hermite(xm1: f32, x0: f32, x1: f32, x2: f32, t: f32) -> f32 {
+    let c1 = 0.5 * (x1 - xm1);
+    let c2 = xm1 - 2.5 * x0 + 2.0 * x1 - 0.5 * x2;
+    let c3 = 0.5 * (x2 - xm1) + 1.5 * (x0 - x1);
+    ((c3 * t + c2) * t + c1) * t + x0
+}
+
+/// Fixed-ratio windowed-sinc resampler for converting between a device rate and 48 kHz.
+///
+/// The read position advances by exactly `from/to` input frames per output frame using
+/// integer arithmetic, so the conversion never drifts. Filter coefficients come from a
+/// Kaiser-windowed sinc table with the phase linearly interpolated between rows; the
+/// cutoff sits below the lower rate's Nyquist so downsampling does not alias.
+pub struct RateConverter {
+    channels: usize,
+    step: usize,      // Whole input frames per output frame
+    step_frac: u64,   // Remainder of the step, in 1/den frames
+    den: u64,
+    half: usize,      // Taps per side
+    table: Vec<f32>,  // (SINC_PHASES + 1) rows of 2 * half taps
+    input: Vec<f32>,  // Interleaved, starting at the oldest frame still under the filter
+    pos: usize,       // Frame in `input` at or before the read position
+    frac: u64,        // Read position past `pos`, in 1/den frames
+}
+
+impl RateConverter {
+    pub fn new(from_rate: u32, to_rate: u32, channels: usize) -> Self {
+        let g = gcd(from_rate as u64, to_rate as u64).max(1);
+        let (num, den) = (from_rate as u64 / g, to_rate as u64 / g);
+        let cutoff = SINC_PASSBAND * (to_rate as f64 / from_rate as f64).min(1.0);
+        let half = (SINC_ZERO_CROSSINGS / cutoff).ceil() as usize;
+        let taps = 2 * half;
+
+        let norm = bessel_i0(KAISER_BETA);
+        let mut table = Vec::with_capacity((SINC_PHASES + 1) * taps);
+        for p in 0..=SINC_PHASES {
+            let phase = p as f64 / SINC_PHASES as f64;
+            let row: Vec<f64> = (0..taps)
+                .map(|k| {
+                    let t = k as f64 - (half - 1) as f64 - phase;
+                    let x = t / half as f64;
+                    if x.abs() >= 1.0 {
+                        return 0.0;
+                    }
+                    let window = bessel_i0(KAISER_BETA * (1.0 - x * x).sqrt()) / norm;
+                    cutoff * sinc(cutoff * t) * window
+                })
+                .collect();
+            // Unity gain at DC for every phase
+            let sum: f64 = row.iter().sum();
+            table.extend(row.iter().map(|c| (c / sum) as f32));
+        }
+
+        let channels = channels.max(1);
+        // Filter history plus a full device callback, so `process` stays allocation-free
+        let mut input = Vec::with_capacity((2 * taps + 8192) * channels);
+        input.resize((half - 1) * channels, 0.0); // History so the first input frame is centred
+        Self {
+            channels,
+            step: (num / den) as usize,
+            step_frac: num % den,
+            den,
+            half,
+            table,
+            input,
+            pos: half - 1,
+            frac: 0,
+        }
+    }
+
+    /// Convert interleaved `input`, appending every output frame it completes to `out`
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        self.input.extend_from_slice(input);
+        let frames = self.input.len() / self.channels;
+        let taps = 2 * self.half;
+
+        while self.pos + self.half < frames {
+            let phase = self.frac as f64 / self.den as f64 * SINC_PHASES as f64;
+            let row = (phase as usize).min(SINC_PHASES - 1);
+            let w = (phase - row as f64) as f32;
+            let (a, b) = (&self.table[row * taps..(row + 1) * taps], &self.table[(row + 1) * taps..(row + 2) * taps]);
+            let first = (self.pos + 1 - self.half) * self.channels;
+
+            let start = out.len();
+            out.resize(start + self.channels, 0.0);
+            for (k, (ca, cb)) in a.iter().zip(b).enumerate() {
+                let coeff = ca + (cb - ca) * w;
+                let frame = &self.input[first + k * self.channels..first + (k + 1) * self.channels];
+                for (o, s) in out[start..].iter_mut().zip(frame) {
+                    *o += s * coeff;
+                }
+            }
+
+            self.pos += self.step;
+            self.frac += self.step_frac;
+            if self.frac >= self.den {
+                self.frac -= self.den;
+                self.pos += 1;
+            }
+        }
+
+        // Drop frames that have left the filter
+        let consumed = (self.pos + 1).saturating_sub(self.half).min(frames);
+        self.input.drain(..consumed * self.channels);
+        self.pos -= consumed;
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-12 {
+        1.0
+    } else {
+        let px = std::f64::consts::PI * x;
+        px.sin() / px
+    }
+}
+
+// Zeroth-order modified Bessel function of the first kind (power series)
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let y = x * x / 4.0;
+    for k in 1..50 {
+        term *= y / (k * k) as f64;
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
@@ -0,0 +1,484 @@
+// 네트워크 장애 시뮬레이터 - 수신 파이프라인을 실제 네트워크 없이 재현 가능하게 테스트
+#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+use crate::peer::{
+    self, FrameDuration, JitterBuffer, PeerReceiver, CHANNELS, FRAME_SIZE, SAMPLE_RATE,
+};
+use crate::codec::{CodecProfile, PayloadCodec};
+use crate::udp::AudioPacketHeader;
+
+// Seeded PRNG (SplitMix64) so every run with the same seed is identical
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+
+    /// Standard normal (Box-Muller)
+    pub fn normal(&mut self) -> f64 {
+        let u1 = self.next_f64().max(1e-12);
+        let u2 = self.next_f64();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+// Two-state burst loss model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GilbertElliott {
+    pub p_good_to_bad: f64,
+    pub p_bad_to_good: f64,
+    pub loss_good: f64,
+    pub loss_bad: f64,
+}
+
+impl GilbertElliott {
+    pub fn none() -> Self {
+        Self { p_good_to_bad: 0.0, p_bad_to_good: 1.0, loss_good: 0.0, loss_bad: 0.0 }
+    }
+
+    /// Independent (Bernoulli) loss
+    pub fn random(loss: f64) -> Self {
+        Self { p_good_to_bad: 0.0, p_bad_to_good: 1.0, loss_good: loss, loss_bad: loss }
+    }
+}
+
+// Extra one-way delay added to each packet, on top of the base delay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum JitterDistribution {
+    None,
+    Uniform { max_ms: f64 },
+    Normal { std_ms: f64 },
+    Pareto { scale_ms: f64, shape: f64 }, // Heavy tail, e.g. Wi-Fi retransmissions
+}
+
+impl JitterDistribution {
+    fn sample_ms(&self, rng: &mut SimRng) -> f64 {
+        match *self {
+            JitterDistribution::None => 0.0,
+            JitterDistribution::Uniform { max_ms } => rng.next_f64() * max_ms,
+            JitterDistribution::Normal { std_ms } => rng.normal() * std_ms,
+            JitterDistribution::Pareto { scale_ms, shape } => {
+                let u = rng.next_f64().max(1e-12);
+                scale_ms * (u.powf(-1.0 / shape) - 1.0)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpairmentConfig {
+    pub seed: u64,
+    pub base_delay_ms: f64,
+    pub loss: GilbertElliott,
+    pub jitter: JitterDistribution,
+    pub reorder_prob: f64,
+    pub reorder_delay_ms: f64, // Held back by this much when reordered
+    pub duplicate_prob: f64,
+    pub clock_skew_ppm: f64,   // Sender clock speed relative to ours (+ = faster)
+    pub bitrate_kbps: u32,
+    pub initial_delay_ms: f32, // Jitter buffer starting target
+    #[serde(default)]
+    pub profile: CodecProfile,
+    #[serde(skip)]
+    pub frame: FrameDuration,  // Sender frame; playout stays at one output period per tick
+}
+
+impl ImpairmentConfig {
+    pub fn clean(seed: u64) -> Self {
+        Self {
+            seed,
+            base_delay_ms: 20.0,
+            loss: GilbertElliott::none(),
+            jitter: JitterDistribution::None,
+            reorder_prob: 0.0,
+            reorder_delay_ms: 10.0,
+            duplicate_prob: 0.0,
+            clock_skew_ppm: 0.0,
+            bitrate_kbps: 96,
+            initial_delay_ms: 10.0,
+            profile: CodecProfile::default(),
+            frame: FrameDuration::default(),
+        }
+    }
+
+    /// Home Wi-Fi: moderate normal jitter, occasional short bursts of loss
+    pub fn wifi(seed: u64) -> Self {
+        Self {
+            loss: GilbertElliott { p_good_to_bad: 0.01, p_bad_to_good: 0.3, loss_good: 0.001, loss_bad: 0.5 },
+            jitter: JitterDistribution::Normal { std_ms: 3.0 },
+            reorder_prob: 0.01,
+            duplicate_prob: 0.001,
+            clock_skew_ppm: 50.0,
+            ..Self::clean(seed)
+        }
+    }
+
+    /// Congested uplink: heavy-tailed delay spikes and frequent bursts
+    pub fn congested(seed: u64) -> Self {
+        Self {
+            base_delay_ms: 40.0,
+            loss: GilbertElliott { p_good_to_bad: 0.03, p_bad_to_good: 0.2, loss_good: 0.005, loss_bad: 0.6 },
+            jitter: JitterDistribution::Pareto { scale_ms: 4.0, shape: 2.5 },
+            reorder_prob: 0.03,
+            reorder_delay_ms: 15.0,
+            duplicate_prob: 0.005,
+            clock_skew_ppm: -120.0,
+            ..Self::clean(seed)
+        }
+    }
+
+    pub fn preset(name: &str, seed: u64) -> Option<Self> {
+        match name {
+            "clean" => Some(Self::clean(seed)),
+            "wifi" => Some(Self::wifi(seed)),
+            "congested" => Some(Self::congested(seed)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SimReport {
+    pub frames_sent: u32,
+    pub packets_dropped: u32,    // Lost by the simulated network
+    pub packets_duplicated: u32, // Extra copies injected by the network
+    pub packets_reordered: u32,
+    pub frames_played: u32,
+    pub frames_concealed: u32,
+    pub fec_recovered: u32,
+    pub packets_late: u32,
+    pub underruns: u32,          // Output periods with nothing to play
+    pub glitches: u32,           // Underrun runs + concealment events
+    pub mean_added_delay_ms: f32, // Jitter buffer depth at playout
+    pub max_added_delay_ms: f32,
+    pub final_target_ms: f32,
+    pub drift_ppm: f32,          // Receiver's final sender clock estimate
+}
+
+pub struct SimOutput {
+    pub report: SimReport,
+    pub output: Vec<f32>, // Interleaved stereo, receiver clock, starting at the first send
+}
+
+pub struct NetworkSimulator {
+    config: ImpairmentConfig,
+    rng: SimRng,
+    in_bad_state: bool,
+}
+
+impl NetworkSimulator {
+    pub fn new(config: ImpairmentConfig) -> Self {
+        let rng = SimRng::new(config.seed);
+        Self { config, rng, in_bad_state: false }
+    }
+
+    // Gilbert-Elliott state transition followed by the loss draw for this packet
+    fn is_lost(&mut self) -> bool {
+        let ge = &self.config.loss;
+        if self.in_bad_state {
+            if self.rng.chance(ge.p_bad_to_good) { self.in_bad_state = false; }
+        } else if self.rng.chance(ge.p_good_to_bad) {
+            self.in_bad_state = true;
+        }
+        let loss = if self.in_bad_state { ge.loss_bad } else { ge.loss_good };
+        self.rng.chance(loss)
+    }
+
+    // Arrival times (µs, receiver clock) for one packet: none, one, or two if duplicated
+    fn transmit(&mut self, send_us: f64, report: &mut SimReport) -> Vec<u64> {
+        if self.is_lost() {
+            report.packets_dropped += 1;
+            return Vec::new();
+        }
+        let jitter_ms = self.config.jitter.sample_ms(&mut self.rng);
+        let mut delay_ms = (self.config.base_delay_ms + jitter_ms).max(0.0);
+        if self.rng.chance(self.config.reorder_prob) {
+            delay_ms += self.config.reorder_delay_ms;
+            report.packets_reordered += 1;
+        }
+        let arrival = send_us + delay_ms * 1000.0;
+        let mut arrivals = vec![arrival as u64];
+        if self.rng.chance(self.config.duplicate_prob) {
+            report.packets_duplicated += 1;
+            arrivals.push((arrival + self.rng.next_f64() * 5000.0) as u64);
+        }
+        arrivals
+    }
+
+    /// Push `input` (interleaved stereo, 48kHz) through encoder, network and receive pipeline
+    pub fn run(&mut self, input: &[f32]) -> Result<SimOutput, String> {
+        let mut report = SimReport::default();
+        let frame = self.config.frame;
+        let frame_len = frame.frame_len();
+        let frame_samples = frame.samples_per_channel();
+        let frame_us = frame_samples as f64 * 1_000_000.0 / SAMPLE_RATE as f64;
+        let period_us = (FRAME_SIZE / CHANNELS) as f64 * 1_000_000.0 / SAMPLE_RATE as f64;
+        // A fast sender clock produces frames sooner on our clock
+        let sender_period_us = frame_us / (1.0 + self.config.clock_skew_ppm * 1e-6);
+        let total_frames = input.len() / frame_len;
+
+        let mut encoder = peer::create_encoder(PayloadCodec::Opus, frame, &self.config.profile, self.config.bitrate_kbps)?;
+        let mut receiver = PeerReceiver::new()?;
+        let mut jitter = JitterBuffer::new(self.config.initial_delay_ms);
+        let clock_origin = Instant::now();
+
+        let mut in_flight: BinaryHeap<Reverse<(u64, u64, Vec<u8>)>> = BinaryHeap::new();
+        let mut packet_order = 0u64;
+        let mut next_frame = 0usize;
+        let mut lost_total = 0u32;
+        let mut received_total = 0u32;
+
+        let mut output = Vec::with_capacity(input.len() + FRAME_SIZE * 100);
+        let mut period = [0.0f32; FRAME_SIZE];
+        let mut playing = false;
+        let mut in_underrun = false;
+        let mut delay_sum = 0.0f64;
+        let mut delay_count = 0u32;
+        // Keep pulling for a while after the last send so in-flight audio drains
+        let tail_ticks = ((self.config.base_delay_ms + 200.0) * 1000.0 / period_us) as usize;
+        let total_ticks = (total_frames as f64 * sender_period_us / period_us) as usize + tail_ticks;
+
+        for tick in 0..total_ticks {
+            let now_us = tick as f64 * period_us;
+
+            // 1. Sender: encode and transmit every frame captured by now
+            while next_frame < total_frames && next_frame as f64 * sender_period_us <= now_us {
+                let samples = &input[next_frame * frame_len..(next_frame + 1) * frame_len];
+                if report.frames_sent > 0 && report.frames_sent % frame.frames_per_second() == 0 {
+                    if let Some(fec_pct) = peer::adaptive_fec_percent(lost_total, received_total) {
+                        encoder.set_packet_loss_perc(fec_pct).ok();
+                    }
+                }
+                let payload = encoder.encode(samples)?;
+                let header = AudioPacketHeader {
+                    sequence: next_frame as u32,
+                    timestamp: peer::media_timestamp_us((next_frame * frame_samples) as u64),
+                    sample_rate: SAMPLE_RATE,
+                    channels: CHANNELS as u8,
+                    stream_id: 0,
+                    codec: PayloadCodec::Opus.id(),
+                    frame_samples: frame_samples as u16,
+                    payload_len: payload.len() as u16,
+                };
+                let mut packet = header.to_bytes();
+                packet.extend_from_slice(&payload);
+                let send_us = next_frame as f64 * sender_period_us;
+                for arrival in self.transmit(send_us, &mut report) {
+                    in_flight.push(Reverse((arrival, packet_order, packet.clone())));
+                    packet_order += 1;
+                }
+                report.frames_sent += 1;
+                next_frame += 1;
+            }
+
+            // 2. Network: deliver everything that has arrived by now
+            while in_flight.peek().is_some_and(|Reverse((arrival, _, _))| *arrival as f64 <= now_us) {
+                let Some(Reverse((arrival, _, packet))) = in_flight.pop() else { break };
+                let Some(header) = AudioPacketHeader::from_bytes(&packet) else { continue };
+                let payload = &packet[AudioPacketHeader::SIZE..];
+                let arrival_instant = clock_origin + Duration::from_micros(arrival);
+                if let Ok(frame) = receiver.receive(&mut jitter, &header, payload, arrival_instant) {
+                    received_total += 1;
+                    lost_total += frame.lost;
+                    if frame.fec_recovered {
+                        report.fec_recovered += 1;
+                    }
+                }
+            }
+
+            // 3. Playout: one frame per output period, on our clock
+            if playing {
+                delay_sum += jitter.buffered_ms() as f64;
+                delay_count += 1;
+                report.max_added_delay_ms = report.max_added_delay_ms.max(jitter.buffered_ms());
+            }
+            if jitter.pull(&mut period, clock_origin + Duration::from_micros(now_us as u64)) {
+                playing = true;
+                in_underrun = false;
+                report.frames_played += 1;
+                output.extend_from_slice(&period);
+            } else {
+                if playing && next_frame < total_frames {
+                    report.underruns += 1;
+                    if !in_underrun {
+                        report.glitches += 1;
+                        in_underrun = true;
+                    }
+                }
+                output.resize(output.len() + FRAME_SIZE, 0.0);
+            }
+        }
+
+        let js = jitter.stats();
+        report.frames_concealed = js.expand_ops;
+        report.packets_late = js.packets_late;
+        report.glitches += js.concealment_events;
+        report.mean_added_delay_ms = if delay_count > 0 { (delay_sum / delay_count as f64) as f32 } else { 0.0 };
+        report.final_target_ms = jitter.target_ms();
+        report.drift_ppm = jitter.drift_ppm();
+
+        Ok(SimOutput { report, output })
+    }
+}
+
+/// Stereo sine test signal at 48kHz
+pub fn sine_wave(freq_hz: f32, seconds: f32, amplitude: f32) -> Vec<f32> {
+    let frames = (seconds * SAMPLE_RATE as f32) as usize;
+    let mut samples = Vec::with_capacity(frames * CHANNELS);
+    for n in 0..frames {
+        let v = amplitude * (2.0 * std::f32::consts::PI * freq_hz * n as f32 / SAMPLE_RATE as f32).sin();
+        for _ in 0..CHANNELS {
+            samples.push(v);
+        }
+    }
+    samples
+}
+
+/// Deterministic stereo test signal: quarter-second harmonic notes with pitch drawn from `seed`.
+/// Unlike a steady sine it has a single correlation peak, so time alignment is unambiguous.
+pub fn note_sequence(seconds: f32, seed: u64) -> Vec<f32> {
+    let mut rng = SimRng::new(seed);
+    let frames = (seconds * SAMPLE_RATE as f32) as usize;
+    let note_len = SAMPLE_RATE as usize / 4;
+    let ramp = SAMPLE_RATE as f64 * 0.01;
+    let mut samples = Vec::with_capacity(frames * CHANNELS);
+    let mut freq = 220.0f64;
+    let mut phase = 0.0f64;
+    for n in 0..frames {
+        let pos = n % note_len;
+        if pos == 0 {
+            freq = 110.0 * 2f64.powf(rng.next_f64() * 4.0);
+        }
+        let attack = (pos as f64 / ramp).min(1.0);
+        let release = ((note_len - pos) as f64 / ramp).min(1.0);
+        let env = attack * release * (-(pos as f64) / SAMPLE_RATE as f64 * 6.0).exp();
+        phase += 2.0 * std::f64::consts::PI * freq / SAMPLE_RATE as f64;
+        let v = (1..=4).map(|h| (h as f64 * phase).sin() / h as f64).sum::<f64>() * 0.3 * env;
+        for _ in 0..CHANNELS {
+            samples.push(v as f32);
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::CodecPreset;
+
+    fn run(config: ImpairmentConfig) -> SimReport {
+        let input = sine_wave(440.0, 4.0, 0.5);
+        NetworkSimulator::new(config).run(&input).expect("simulation").report
+    }
+
+    #[test]
+    fn same_seed_is_reproducible() {
+        assert_eq!(run(ImpairmentConfig::congested(7)), run(ImpairmentConfig::congested(7)));
+    }
+
+    #[test]
+    fn clean_network_has_no_glitches() {
+        let report = run(ImpairmentConfig::clean(1));
+        assert_eq!(report.packets_dropped, 0);
+        assert_eq!(report.frames_concealed, 0);
+        assert_eq!(report.glitches, 0);
+        assert_eq!(report.frames_played, report.frames_sent);
+    }
+
+    #[test]
+    fn burst_loss_is_concealed_and_recovered() {
+        // In-band FEC only exists in SILK, which needs 10ms frames or longer
+        let mut config = ImpairmentConfig::clean(3);
+        config.profile = CodecPreset::VoiceBadNetwork.profile();
+        config.frame = FrameDuration::Ms20;
+        config.loss = GilbertElliott { p_good_to_bad: 0.05, p_bad_to_good: 0.5, loss_good: 0.0, loss_bad: 0.8 };
+        let report = run(config);
+        assert!(report.packets_dropped > 0);
+        assert!(report.fec_recovered > 0);
+        assert!(report.frames_concealed > 0);
+    }
+
+    #[test]
+    fn celt_only_losses_are_not_counted_as_fec() {
+        let mut config = ImpairmentConfig::clean(3);
+        config.loss = GilbertElliott { p_good_to_bad: 0.05, p_bad_to_good: 0.5, loss_good: 0.0, loss_bad: 0.8 };
+        let report = run(config);
+        assert!(report.packets_dropped > 0);
+        assert_eq!(report.fec_recovered, 0);
+    }
+
+    #[test]
+    fn reordered_packets_are_not_lost() {
+        let mut config = ImpairmentConfig::clean(5);
+        config.initial_delay_ms = 20.0;
+        config.reorder_prob = 0.05;
+        config.reorder_delay_ms = 7.0;
+        let report = run(config);
+        assert!(report.packets_reordered > 0);
+        assert_eq!(report.packets_late, 0);
+        assert_eq!(report.frames_played, report.frames_sent);
+    }
+
+    #[test]
+    fn duplicates_are_dropped() {
+        let mut config = ImpairmentConfig::clean(11);
+        config.duplicate_prob = 0.05;
+        let report = run(config);
+        assert!(report.packets_duplicated > 0);
+        assert_eq!(report.frames_played, report.frames_sent);
+    }
+
+    #[test]
+    fn jitter_grows_the_buffer_target() {
+        let clean = run(ImpairmentConfig::clean(9));
+        let mut config = ImpairmentConfig::clean(9);
+        config.jitter = JitterDistribution::Normal { std_ms: 8.0 };
+        let jittery = run(config);
+        assert!(jittery.final_target_ms > clean.final_target_ms);
+    }
+
+    #[test]
+    fn clock_drift_is_absorbed_by_resampling() {
+        let input = sine_wave(440.0, 20.0, 0.5);
+        for skew_ppm in [-500.0, 500.0] {
+            let mut config = ImpairmentConfig::clean(13);
+            config.clock_skew_ppm = skew_ppm;
+            let report = NetworkSimulator::new(config).run(&input).expect("simulation").report;
+            assert_eq!(report.underruns, 0, "skew {}", skew_ppm);
+            assert_eq!(report.glitches, 0, "skew {}", skew_ppm);
+            assert!((report.drift_ppm as f64 - skew_ppm).abs() < 150.0, "skew {} estimated {}", skew_ppm, report.drift_ppm);
+        }
+    }
+
+    #[test]
+    fn adaptive_fec_tracks_loss() {
+        assert_eq!(peer::adaptive_fec_percent(0, 0), None);
+        assert_eq!(peer::adaptive_fec_percent(0, 100), Some(5));
+        assert_eq!(peer::adaptive_fec_percent(10, 90), Some(20));
+        assert_eq!(peer::adaptive_fec_percent(90, 10), Some(50));
+    }
+}
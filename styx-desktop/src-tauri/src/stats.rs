@@ -0,0 +1,99 @@
+// 스트림 통계 모듈 (WebRTC getStats 스타일)
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::peer::{JitterBuffer, PeerId};
+
+// 피어별 수신 카운터 (네트워크 스레드에서 갱신)
+#[derive(Default, Clone)]
+pub struct PeerStats {
+    pub packets_received: u32,
+    pub packets_lost: u32,
+    pub last_seq: u32,
+    pub audio_level: f32,
+    pub bytes_received: u64,
+    pub fec_recovered: u32,
+}
+
+// 수신 스트림 통계
+#[derive(Debug, Clone, Serialize)]
+pub struct InboundStreamStats {
+    pub peer_id: String,
+    pub packets_received: u32,
+    pub packets_lost: u32,
+    pub bytes_received: u64,
+    pub jitter_ms: f32,
+    pub clock_drift_ppm: f32,
+    pub playout_delay_ms: f32,
+    pub target_delay_ms: f32,
+    pub min_delay_ms: f32, // Playout delay constraints in effect for this peer
+    pub max_delay_ms: f32,
+    pub concealed_samples: u64,
+    pub concealment_events: u32,
+    pub fec_recovered: u32,
+    pub packets_late: u32,
+    pub packets_discarded: u32,
+    pub packets_duplicated: u32,
+    pub accelerate_ops: u32,
+    pub expand_ops: u32,
+    pub audio_level: f32,
+}
+
+// 송신 스트림 통계 (송신 트랙마다 하나)
+#[derive(Debug, Clone, Serialize)]
+pub struct OutboundStreamStats {
+    pub stream_id: u8,
+    pub bitrate_kbps: u32,
+    pub fec_percent: u32,
+    pub packets_sent: u32,
+    pub bytes_sent: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsSnapshot {
+    pub timestamp_ms: u64,
+    pub inbound: Vec<InboundStreamStats>,
+    pub outbound: Vec<OutboundStreamStats>,
+}
+
+// 피어 카운터와 지터 버퍼 상태를 하나의 스냅샷으로 합침
+pub fn build_snapshot(
+    peer_stats: &BTreeMap<PeerId, PeerStats>,
+    jitter_buffers: &BTreeMap<PeerId, JitterBuffer>,
+    outbound: Vec<OutboundStreamStats>,
+) -> StatsSnapshot {
+    let inbound = peer_stats.iter().map(|(peer_id, s)| {
+        let jb = jitter_buffers.get(peer_id);
+        let js = jb.map(|b| b.stats()).unwrap_or_default();
+        InboundStreamStats {
+            peer_id: peer_id.clone(),
+            packets_received: s.packets_received,
+            packets_lost: s.packets_lost,
+            bytes_received: s.bytes_received,
+            jitter_ms: jb.map(|b| b.jitter_ms()).unwrap_or(0.0),
+            clock_drift_ppm: jb.map(|b| b.drift_ppm()).unwrap_or(0.0),
+            playout_delay_ms: jb.map(|b| b.buffered_ms()).unwrap_or(0.0),
+            target_delay_ms: jb.map(|b| b.target_ms()).unwrap_or(0.0),
+            min_delay_ms: jb.map(|b| b.delay_bounds().min_ms).unwrap_or(0.0),
+            max_delay_ms: jb.map(|b| b.delay_bounds().max_ms).unwrap_or(0.0),
+            concealed_samples: js.concealed_samples,
+            concealment_events: js.concealment_events,
+            fec_recovered: s.fec_recovered,
+            packets_late: js.packets_late,
+            packets_discarded: js.packets_discarded,
+            packets_duplicated: js.packets_duplicated,
+            accelerate_ops: js.accelerate_ops,
+            expand_ops: js.expand_ops,
+            audio_level: s.audio_level,
+        }
+    }).collect();
+
+    StatsSnapshot {
+        timestamp_ms: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        inbound,
+        outbound,
+    }
+}
@@ -156,3 +156,34 @@ pub fn bytes_to_samples(bytes: &[u8]) -> Vec<f32> {
         .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
         .collect()
 }
+
+// f32 샘플 → 부호 있는 정수 (bits 비트, 클리핑)
+pub fn quantize(sample: f32, bits: u32) -> i32 {
+    let scale = ((1i32 << (bits - 1)) - 1) as f32;
+    (sample.clamp(-1.0, 1.0) * scale).round() as i32
+}
+
+// 정수 → f32 샘플
+pub fn dequantize(value: i32, bits: u32) -> f32 {
+    value as f32 / ((1i32 << (bits - 1)) - 1) as f32
+}
+
+// f32 샘플을 16/24비트 리틀 엔디언 정수 PCM으로 변환
+pub fn samples_to_pcm(samples: &[f32], bits: u32) -> Vec<u8> {
+    let width = (bits / 8) as usize;
+    samples.iter()
+        .flat_map(|&s| quantize(s, bits).to_le_bytes().into_iter().take(width))
+        .collect()
+}
+
+// 16/24비트 리틀 엔디언 정수 PCM을 f32 샘플로 변환
+pub fn pcm_to_samples(bytes: &[u8], bits: u32) -> Vec<f32> {
+    let width = (bits / 8) as usize;
+    bytes.chunks_exact(width)
+        .map(|chunk| {
+            let mut word = [0u8; 4];
+            word[4 - width..].copy_from_slice(chunk);
+            dequantize(i32::from_le_bytes(word) >> (32 - bits), bits) // Sign-extends
+        })
+        .collect()
+}
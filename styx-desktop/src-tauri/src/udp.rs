@@ -9,14 +9,17 @@ use tokio::time::timeout;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioPacketHeader {
     pub sequence: u32,      // 시퀀스 번호
-    pub timestamp: u64,     // 타임스탬프 (마이크로초)
+    pub timestamp: u64,     // 송신측 미디어 타임스탬프 (마이크로초)
     pub sample_rate: u32,   // 샘플레이트
     pub channels: u8,       // 채널 수
+    pub stream_id: u8,      // 송신 트랙 (0 = 기본 입력)
+    pub codec: u8,          // 페이로드 코덱 (0 = Opus, 1 = PCM16, 2 = PCM24, 3 = 무손실)
+    pub frame_samples: u16, // 프레임 길이 (채널당 샘플 수, 48kHz에서 120 = 2.5ms ~ 2880 = 60ms)
     pub payload_len: u16,   // 페이로드 길이
 }
 
 impl AudioPacketHeader {
-    pub const SIZE: usize = 19; // 4 + 8 + 4 + 1 + 2
+    pub const SIZE: usize = 23; // 4 + 8 + 4 + 1 + 1 + 1 + 2 + 2
     
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(Self::SIZE);
@@ -24,6 +27,9 @@ impl AudioPacketHeader {
         buf.extend_from_slice(&self.timestamp.to_be_bytes());
         buf.extend_from_slice(&self.sample_rate.to_be_bytes());
         buf.push(self.channels);
+        buf.push(self.stream_id);
+        buf.push(self.codec);
+        buf.extend_from_slice(&self.frame_samples.to_be_bytes());
         buf.extend_from_slice(&self.payload_len.to_be_bytes());
         buf
     }
@@ -40,7 +46,10 @@ impl AudioPacketHeader {
             ]),
             sample_rate: u32::from_be_bytes([data[12], data[13], data[14], data[15]]),
             channels: data[16],
-            payload_len: u16::from_be_bytes([data[17], data[18]]),
+            stream_id: data[17],
+            codec: data[18],
+            frame_samples: u16::from_be_bytes([data[19], data[20]]),
+            payload_len: u16::from_be_bytes([data[21], data[22]]),
         })
     }
 }
@@ -127,6 +136,9 @@ pub async fn send_audio_packet(
         timestamp,
         sample_rate: 48000,
         channels: 2,
+        stream_id: 0,
+        codec: 0,           // Opus
+        frame_samples: 240, // 5ms
         payload_len: audio_data.len() as u16,
     };
     